@@ -0,0 +1,60 @@
+//! Comparison of the default hash builder with a fast one.
+//!
+//! Run via `cargo bench`.
+
+use std::hash::{BuildHasher, BuildHasherDefault, Hasher};
+use std::hint::black_box;
+use std::time::Instant;
+
+use options::Options;
+
+const NAMES: usize = 100;
+const ROUNDS: usize = 10_000;
+
+struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Fnv(0xcbf29ce484222325)
+    }
+}
+
+impl Hasher for Fnv {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+}
+
+fn main() {
+    let names = (0..NAMES).map(|i| format!("name{i}")).collect::<Vec<_>>();
+    run("default", Options::new(), &names);
+    run(
+        "fnv",
+        Options::with_hasher(BuildHasherDefault::<Fnv>::default()),
+        &names,
+    );
+}
+
+fn run<S: BuildHasher>(label: &str, mut options: Options<S>, names: &[String]) {
+    for (i, name) in names.iter().enumerate() {
+        options.set(name.as_str(), i);
+    }
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        for name in names {
+            black_box(options.get::<usize>(black_box(name)));
+        }
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "{label}: {:.1} ns per lookup",
+        elapsed.as_nanos() as f64 / (ROUNDS * names.len()) as f64,
+    );
+}
@@ -0,0 +1,57 @@
+//! Reduction of memory allocated for names by interning static ones.
+//!
+//! Run via `cargo bench`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use options::Options;
+
+const KNOWN: &[&str] = &[
+    "address",
+    "buffer_size",
+    "connection_timeout",
+    "keep_alive",
+    "maximum_connections",
+    "retry_count",
+    "retry_interval",
+    "verbose",
+];
+
+struct Counter;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counter {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, pointer: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(pointer, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counter = Counter;
+
+fn main() {
+    let mut bags = Vec::new();
+    let start = ALLOCATED.load(Ordering::Relaxed);
+    for _ in 0..1000 {
+        let mut options = Options::new();
+        for (i, name) in KNOWN.iter().enumerate() {
+            options.set(name.to_string(), i);
+        }
+        bags.push(options);
+    }
+    let before = ALLOCATED.load(Ordering::Relaxed) - start;
+    for options in bags.iter_mut() {
+        options.intern_static(KNOWN);
+    }
+    let after = ALLOCATED.load(Ordering::Relaxed) - start;
+    println!("before: {before} bytes");
+    println!("after: {after} bytes");
+}
@@ -0,0 +1,406 @@
+//! JSON documents.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+const MAX_DEPTH: usize = 128;
+
+/// An error in a JSON document.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct JsonError {
+    /// The byte offset at which the document stops being valid.
+    pub position: usize,
+}
+
+/// A JSON document.
+///
+/// Integers are kept apart from other numbers so that they survive a round
+/// trip through text without turning into floating-point numbers.
+///
+/// The type is defined here rather than taken from `serde_json` so that the
+/// package stays free of dependencies, and it is available only with the
+/// `json` feature. It covers what the conversions of `Options` need and is not
+/// meant as a general-purpose replacement.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    /// A null.
+    Null,
+    /// A boolean.
+    Bool(bool),
+    /// A number without a fractional part or an exponent.
+    Integer(i64),
+    /// Any other number.
+    Float(f64),
+    /// A string.
+    String(String),
+    /// An array.
+    Array(Vec<Json>),
+    /// An object with members ordered by name.
+    Object(BTreeMap<String, Json>),
+}
+
+impl fmt::Display for Json {
+    /// Write a compact textual representation.
+    ///
+    /// Non-finite floating-point numbers, which JSON cannot represent, are
+    /// written as `null`.
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Json::Null => formatter.write_str("null"),
+            Json::Bool(value) => write!(formatter, "{value}"),
+            Json::Integer(value) => write!(formatter, "{value}"),
+            Json::Float(value) if value.is_finite() => write!(formatter, "{value:?}"),
+            Json::Float(_) => formatter.write_str("null"),
+            Json::String(value) => write_string(formatter, value),
+            Json::Array(values) => {
+                formatter.write_str("[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        formatter.write_str(",")?;
+                    }
+                    write!(formatter, "{value}")?;
+                }
+                formatter.write_str("]")
+            }
+            Json::Object(members) => {
+                formatter.write_str("{")?;
+                for (i, (name, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        formatter.write_str(",")?;
+                    }
+                    write_string(formatter, name)?;
+                    write!(formatter, ":{value}")?;
+                }
+                formatter.write_str("}")
+            }
+        }
+    }
+}
+
+impl FromStr for Json {
+    type Err = JsonError;
+
+    /// Parse a textual representation.
+    ///
+    /// Numbers without a fractional part or an exponent that fit in `i64` are
+    /// parsed as integers, and the other numbers as floating-point numbers.
+    /// Arrays and objects nested more than 128 levels deep are rejected.
+    fn from_str(text: &str) -> Result<Json, JsonError> {
+        let mut parser = Parser {
+            text,
+            position: 0,
+            depth: 0,
+        };
+        let value = parser.value()?;
+        parser.skip();
+        match parser.position == text.len() {
+            true => Ok(value),
+            _ => Err(parser.error()),
+        }
+    }
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "invalid JSON at byte {}", self.position)
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+struct Parser<'l> {
+    text: &'l str,
+    position: usize,
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn value(&mut self) -> Result<Json, JsonError> {
+        self.skip();
+        match self.peek().ok_or_else(|| self.error())? {
+            'n' => self.keyword("null", Json::Null),
+            't' => self.keyword("true", Json::Bool(true)),
+            'f' => self.keyword("false", Json::Bool(false)),
+            '"' => self.string().map(Json::String),
+            '[' => self.nested(Parser::array),
+            '{' => self.nested(Parser::object),
+            _ => self.number(),
+        }
+    }
+
+    fn nested(
+        &mut self,
+        parse: fn(&mut Self) -> Result<Json, JsonError>,
+    ) -> Result<Json, JsonError> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error());
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn array(&mut self) -> Result<Json, JsonError> {
+        self.position += 1;
+        let mut values = Vec::new();
+        self.skip();
+        if self.eat(']') {
+            return Ok(Json::Array(values));
+        }
+        loop {
+            values.push(self.value()?);
+            self.skip();
+            if self.eat(']') {
+                return Ok(Json::Array(values));
+            }
+            self.expect(',')?;
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, JsonError> {
+        self.position += 1;
+        let mut members = BTreeMap::new();
+        self.skip();
+        if self.eat('}') {
+            return Ok(Json::Object(members));
+        }
+        loop {
+            self.skip();
+            let name = self.string()?;
+            self.skip();
+            self.expect(':')?;
+            members.insert(name, self.value()?);
+            self.skip();
+            if self.eat('}') {
+                return Ok(Json::Object(members));
+            }
+            self.expect(',')?;
+        }
+    }
+
+    fn number(&mut self) -> Result<Json, JsonError> {
+        let start = self.position;
+        let rest = &self.text[start..];
+        let length = rest
+            .find(|character: char| !matches!(character, '0'..='9' | '-' | '+' | '.' | 'e' | 'E'))
+            .unwrap_or(rest.len());
+        let number = &rest[..length];
+        let digits = number.strip_prefix('-').unwrap_or(number);
+        if !digits.starts_with(|character: char| character.is_ascii_digit())
+            || (digits.len() > 1
+                && digits.starts_with('0')
+                && digits[1..].starts_with(|character: char| character.is_ascii_digit()))
+        {
+            return Err(self.error());
+        }
+        self.position += length;
+        if !number.contains(['.', 'e', 'E']) {
+            if let Ok(value) = number.parse() {
+                return Ok(Json::Integer(value));
+            }
+        }
+        match number.parse() {
+            Ok(value) => Ok(Json::Float(value)),
+            _ => Err(JsonError { position: start }),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.expect('"')?;
+        let mut value = String::new();
+        loop {
+            let character = self.peek().ok_or_else(|| self.error())?;
+            self.position += character.len_utf8();
+            match character {
+                '"' => return Ok(value),
+                '\\' => {
+                    let character = self.peek().ok_or_else(|| self.error())?;
+                    self.position += 1;
+                    value.push(match character {
+                        '"' => '"',
+                        '\\' => '\\',
+                        '/' => '/',
+                        'b' => '\u{8}',
+                        'f' => '\u{c}',
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        'u' => self.unicode()?,
+                        _ => {
+                            return Err(JsonError {
+                                position: self.position - 1,
+                            })
+                        }
+                    });
+                }
+                character if character < ' ' => {
+                    return Err(JsonError {
+                        position: self.position - 1,
+                    });
+                }
+                character => value.push(character),
+            }
+        }
+    }
+
+    fn unicode(&mut self) -> Result<char, JsonError> {
+        let high = self.hex()?;
+        let code = match high {
+            0xd800..=0xdbff => {
+                self.expect('\\')?;
+                self.expect('u')?;
+                let low = self.hex()?;
+                if !(0xdc00..=0xdfff).contains(&low) {
+                    return Err(self.error());
+                }
+                0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+            }
+            _ => high,
+        };
+        char::from_u32(code).ok_or_else(|| self.error())
+    }
+
+    fn hex(&mut self) -> Result<u32, JsonError> {
+        match self.text.get(self.position..self.position + 4) {
+            Some(digits)
+                if digits
+                    .chars()
+                    .all(|character| character.is_ascii_hexdigit()) =>
+            {
+                self.position += 4;
+                Ok(u32::from_str_radix(digits, 16).unwrap())
+            }
+            _ => Err(self.error()),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str, value: Json) -> Result<Json, JsonError> {
+        match self.text[self.position..].starts_with(keyword) {
+            true => {
+                self.position += keyword.len();
+                Ok(value)
+            }
+            _ => Err(self.error()),
+        }
+    }
+
+    fn expect(&mut self, character: char) -> Result<(), JsonError> {
+        match self.eat(character) {
+            true => Ok(()),
+            _ => Err(self.error()),
+        }
+    }
+
+    fn eat(&mut self, character: char) -> bool {
+        let found = self.peek() == Some(character);
+        if found {
+            self.position += character.len_utf8();
+        }
+        found
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.position..].chars().next()
+    }
+
+    fn skip(&mut self) {
+        let rest = &self.text[self.position..];
+        self.position += rest.len() - rest.trim_start_matches([' ', '\t', '\n', '\r']).len();
+    }
+
+    fn error(&self) -> JsonError {
+        JsonError {
+            position: self.position,
+        }
+    }
+}
+
+fn write_string(formatter: &mut fmt::Formatter, value: &str) -> fmt::Result {
+    formatter.write_str("\"")?;
+    for character in value.chars() {
+        match character {
+            '"' => formatter.write_str("\\\"")?,
+            '\\' => formatter.write_str("\\\\")?,
+            '\n' => formatter.write_str("\\n")?,
+            '\r' => formatter.write_str("\\r")?,
+            '\t' => formatter.write_str("\\t")?,
+            character if character < ' ' => write!(formatter, "\\u{:04x}", character as u32)?,
+            character => write!(formatter, "{character}")?,
+        }
+    }
+    formatter.write_str("\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Json, JsonError};
+
+    #[test]
+    fn display() {
+        macro_rules! test(
+            ($value:expr, $expected:expr) => (
+                assert_eq!($value.to_string(), $expected)
+            );
+        );
+
+        test!(Json::Null, "null");
+        test!(Json::Integer(-42), "-42");
+        test!(Json::Float(1.0), "1.0");
+        test!(Json::Float(f64::NAN), "null");
+        test!(
+            Json::String("a\"b\\c\nd\u{1}".to_string()),
+            r#""a\"b\\c\nd\u0001""#
+        );
+        test!(
+            Json::Array(vec![Json::Bool(true), Json::Null]),
+            "[true,null]"
+        );
+        test!(Json::Object(Default::default()), "{}");
+    }
+
+    #[test]
+    fn from_str() {
+        macro_rules! test(
+            ($text:expr, $expected:expr) => (
+                assert_eq!($text.parse::<Json>(), $expected)
+            );
+        );
+
+        test!(" null ", Ok(Json::Null));
+        test!("-42", Ok(Json::Integer(-42)));
+        test!("1.0", Ok(Json::Float(1.0)));
+        test!("2e3", Ok(Json::Float(2000.0)));
+        test!(
+            "9223372036854775808",
+            Ok(Json::Float(9223372036854775808.0))
+        );
+        test!(
+            r#""a\"b\u00e9\ud83d\ude00""#,
+            Ok(Json::String("a\"bé😀".to_string()))
+        );
+        test!(
+            r#"{"a": [true, null], "b": {}}"#,
+            Ok(Json::Object(
+                [
+                    (
+                        "a".to_string(),
+                        Json::Array(vec![Json::Bool(true), Json::Null])
+                    ),
+                    ("b".to_string(), Json::Object(Default::default())),
+                ]
+                .into()
+            ))
+        );
+        test!("", Err(JsonError { position: 0 }));
+        test!("[1,]", Err(JsonError { position: 3 }));
+        test!("01", Err(JsonError { position: 0 }));
+        test!("true false", Err(JsonError { position: 5 }));
+
+        let text = format!("{}{}", "[".repeat(128), "]".repeat(128));
+        assert!(text.parse::<Json>().is_ok());
+        test!("[".repeat(200000), Err(JsonError { position: 128 }));
+        test!(r#"{"a":"#.repeat(200000), Err(JsonError { position: 640 }));
+    }
+}
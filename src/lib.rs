@@ -5,7 +5,7 @@
 //! ```
 //! use options::Options;
 //!
-//! let mut options = Options::default();
+//! let mut options = Options::new();
 //!
 //! options
 //!     .set("foo", 42)
@@ -16,20 +16,328 @@
 //! println!("bar = {}", options.get::<&str>("bar").unwrap());
 //! println!("baz = {}", options.get::<String>("baz").unwrap());
 //! ```
+//!
+//! ## Features
+//!
+//! * `json` enables `Json`, `Options::from_json`, and `Options::to_json`.
+//! * `toml` enables `Options::from_toml_str` and `Options::to_toml_string` and
+//!   implies `json`.
+
+use std::any::{Any, TypeId};
+use std::borrow::Cow;
+use std::cell::{Cell, OnceCell};
+use std::cmp::Ordering;
+use std::collections::hash_map::{self, HashMap, Iter, IterMut, RandomState};
+use std::collections::{BTreeMap, HashSet};
+use std::hash::BuildHasher;
+use std::marker::PhantomData;
+use std::mem::size_of;
+use std::net::{IpAddr, SocketAddr};
+use std::ops::{BitOr, Deref, Range};
+use std::rc::Rc;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "json")]
+mod json;
+mod parse;
+pub mod path;
+mod properties;
+#[cfg(feature = "toml")]
+mod toml;
+mod types;
 
-use std::any::Any;
-use std::collections::hash_map::{HashMap, Iter, IterMut};
+#[cfg(feature = "json")]
+pub use json::{Json, JsonError};
+#[cfg(feature = "toml")]
+pub use toml::TomlError;
 
 /// A collection of named parameters.
-#[derive(Debug, Default)]
-pub struct Options(HashMap<Name, Value>);
+///
+/// The parameters are stored in a hash map, and `S` is its hash builder. Nested
+/// options, which are values of type `Options`, use the default hash builder.
+#[derive(Debug)]
+pub struct Options<S = RandomState> {
+    parameters: HashMap<Name, Value, S>,
+    aliases: HashMap<Name, Name>,
+    protected: HashSet<Name>,
+    secrets: HashSet<Name>,
+    policy: KeyPolicy,
+    normalizer: Option<fn(&str) -> String>,
+    traits: HashMap<(TypeId, TypeId), Rc<dyn Any>>,
+    factories: HashMap<TypeId, Rc<dyn Any>>,
+    checkpoints: Vec<(Option<String>, Journal)>,
+    version: u64,
+    sorted_names: Option<(u64, Vec<Name>)>,
+    timestamps: bool,
+    warning_hook: Option<WarningHook>,
+}
+
+/// An immutable collection of named parameters that can be shared between
+/// threads.
+///
+/// It is created by `Options::into_shared`.
+#[derive(Debug)]
+pub struct FrozenOptions {
+    parameters: HashMap<Name, Box<dyn Any + Send + Sync>>,
+}
+
+/// Rules for validating names.
+///
+/// The rules are enforced by `set_checked`. The default policy accepts any name.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct KeyPolicy {
+    /// Whether empty names are rejected.
+    pub non_empty: bool,
+    /// The maximum length of names in bytes.
+    pub max_length: Option<usize>,
+    /// The predicate that every character of names should satisfy.
+    pub allowed: Option<fn(char) -> bool>,
+}
+
+/// An error of validating a name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KeyError {
+    /// The name is empty.
+    Empty,
+    /// The name is longer than the maximum length.
+    TooLong {
+        /// The length of the name.
+        length: usize,
+        /// The maximum length.
+        max_length: usize,
+    },
+    /// The name contains a character that is not allowed.
+    InvalidCharacter(char),
+    /// The name is protected (see `Options::protect`).
+    Protected,
+}
+
+/// An error of getting the value of a parameter.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GetError {
+    /// The parameter is not present.
+    Missing,
+    /// The value is of a different type.
+    Mismatch {
+        /// The type name of the requested value.
+        expected: &'static str,
+        /// The type name of the stored value.
+        actual: &'static str,
+    },
+}
+
+/// An error of getting the value of a parameter and converting it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConvertError<E> {
+    /// The value is unavailable.
+    Get(GetError),
+    /// The conversion has failed.
+    Convert(E),
+}
+
+/// An error of parsing an override (see `Options::apply_overrides`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// The position of the override in the sequence.
+    pub index: usize,
+}
 
 /// A parameter name.
-pub type Name = String;
+///
+/// Names are usually owned strings, but names known in advance can be backed
+/// by static strings; see `Options::intern_static`.
+pub type Name = Cow<'static, str>;
+
+/// A custom check of a value; see `Options::validate_each`.
+pub type Validator<'l> = &'l dyn Fn(&Value) -> Result<(), String>;
+
+/// A path to a nested parameter with a value of a specific type.
+///
+/// ```
+/// # use options::{Options, PathKey};
+/// const DB_PORT: PathKey<u16> = PathKey::new("db.port");
+///
+/// let mut db = Options::new();
+/// db.set("port", 5432u16);
+/// let mut options = Options::new();
+/// options.set("db", db);
+///
+/// assert_eq!(options.get_path_keyed(DB_PORT), Some(5432));
+/// ```
+pub struct PathKey<T> {
+    path: &'static str,
+    phantom: PhantomData<fn() -> T>,
+}
 
 /// A parameter value.
 #[derive(Debug)]
-pub struct Value(Box<dyn Any>);
+pub struct Value {
+    inner: Box<dyn Any>,
+    type_id: TypeId,
+    type_name: &'static str,
+    access: Option<&'static Access>,
+    sequence: u64,
+    modified: u64,
+    metadata: Option<Box<Metadata>>,
+}
+
+/// A value marking a parameter for removal when merging; see
+/// `Options::set_clear`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Clear;
+
+/// A conflict of types between two parameters with the same name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Conflict {
+    /// The name of the parameters.
+    pub name: Name,
+    /// The type name of the existing value.
+    pub existing_type: &'static str,
+    /// The type name of the incoming value.
+    pub incoming_type: &'static str,
+}
+
+/// A strategy of merging options.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// The incoming values overwrite the existing ones.
+    Overwrite,
+    /// The existing values are kept, and only the absent parameters are added.
+    KeepExisting,
+    /// The incoming values overwrite the existing ones of the same type, and
+    /// the conflicts of types are reported (see `Options::try_merge`).
+    ErrorOnConflict,
+    /// Nested options are merged recursively (see `Options::deep_merge`).
+    DeepMerge,
+}
+
+/// A summary of the changes made by `Options::reconcile`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Reconciliation {
+    /// The names of the added parameters in ascending order.
+    pub added: Vec<Name>,
+    /// The names of the removed parameters in ascending order.
+    pub removed: Vec<Name>,
+    /// The names of the updated parameters in ascending order.
+    pub updated: Vec<Name>,
+}
+
+/// A reference to a value of a common category.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TypedRef<'l> {
+    /// An integer that fits in `i64`.
+    Int(i64),
+    /// A floating-point number of type `f32` or `f64`.
+    Float(f64),
+    /// A boolean.
+    Bool(bool),
+    /// A `String` or a `&'static str`.
+    Str(&'l str),
+    /// A value of any other type, which is given by name.
+    Other(&'static str),
+}
+
+/// Statistics about the storage of options.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OptionsStats {
+    /// The number of parameters.
+    pub len: usize,
+    /// The number of parameters that can be held without reallocating.
+    pub capacity: usize,
+    /// The ratio of `len` to `capacity`, which is zero if nothing is allocated.
+    pub load_factor: f64,
+    /// The number of distinct types of values.
+    pub distinct_types: usize,
+}
+
+/// A guard that reverts the changes made through it when dropped.
+///
+/// The guard dereferences to the underlying options for reading. The values
+/// displaced by `set` and `remove` are kept aside and moved back into place
+/// when the guard goes out of scope, with their times and order of insertion
+/// intact.
+pub struct ScopedOptions<'l, S: BuildHasher = RandomState> {
+    options: &'l mut Options<S>,
+}
+
+/// A read-only view of two options where one takes precedence over the other.
+///
+/// The parameters of the top options shadow those of the base options with the
+/// same names. Neither of the two is copied or modified.
+#[derive(Debug)]
+pub struct Overlay<'l, S = RandomState> {
+    base: &'l Options<S>,
+    top: &'l Options<S>,
+}
+
+/// A view of the parameters whose names start with a prefix.
+///
+/// The prefix is prepended to the names given to the view and stripped from the
+/// names listed by it.
+#[derive(Debug)]
+pub struct Namespace<'l, S = RandomState> {
+    options: &'l Options<S>,
+    prefix: &'l str,
+}
+
+/// A mutable view of the parameters whose names start with a prefix.
+///
+/// See `Namespace` for the treatment of names.
+#[derive(Debug)]
+pub struct NamespaceMut<'l, S = RandomState> {
+    options: &'l mut Options<S>,
+    prefix: &'l str,
+}
+
+/// An entry of a nested parameter, which might be absent.
+///
+/// The nested options are treated as in `Options::path_entry_or_insert`.
+#[derive(Debug)]
+pub struct PathEntry<'l, S = RandomState> {
+    options: &'l mut Options<S>,
+    path: &'l str,
+}
+
+// A conversion of a value into a trait object.
+type Caster<T> = Box<dyn Fn(&dyn Any) -> Option<&T>>;
+
+// A clone of a value stored indirectly.
+type Cloner = fn(&dyn Any) -> Box<dyn Any>;
+
+// The rarely used properties of a value, which are boxed to keep values small.
+#[derive(Clone, Debug, Default)]
+struct Metadata {
+    inserted: Option<Instant>,
+    expires: Option<Instant>,
+    source: Option<Rc<str>>,
+}
+
+// The values displaced since a checkpoint by name, where `None` stands for an
+// absent parameter, kept for undoing the changes without cloning up front.
+type Journal = HashMap<Name, Option<Value>>;
+
+// A function receiving warnings.
+#[derive(Clone)]
+struct WarningHook(Rc<dyn Fn(&str)>);
+
+// A value computed on first access. Since `get` and `get_ref` borrow the
+// options immutably, the computed value is kept in a cell.
+struct Lazy<T> {
+    value: OnceCell<T>,
+    initialize: Cell<Option<Box<dyn FnOnce() -> T>>>,
+}
+
+// Functions giving access to a value stored indirectly, which is the computed
+// value of a boxed `Lazy` or the contents of a boxed `Rc`.
+#[derive(Clone, Copy, Debug)]
+struct Access {
+    get: fn(&dyn Any) -> &dyn Any,
+    get_mut: fn(&mut dyn Any) -> &mut dyn Any,
+    into_inner: fn(Box<dyn Any>) -> Box<dyn Any>,
+    clone: Option<Cloner>,
+}
 
 /// An iterator over parameters.
 pub struct Parameters<'l> {
@@ -39,6 +347,13 @@ pub struct Parameters<'l> {
 /// An iterator over mutable parameters.
 pub struct ParametersMut<'l> {
     iterator: IterMut<'l, Name, Value>,
+    version: u64,
+}
+
+/// An iterator over parameters removed from options.
+pub struct Drain<'l> {
+    #[allow(clippy::type_complexity)]
+    iterator: hash_map::ExtractIf<'l, Name, Value, Box<dyn FnMut(&Name, &mut Value) -> bool + 'l>>,
 }
 
 /// An iterator over names.
@@ -48,235 +363,6476 @@ pub struct Names<'l> {
 }
 
 impl Options {
-    /// Get the value of a parameter.
-    #[inline]
-    pub fn get<T: Any + Clone>(&self, name: &str) -> Option<T> {
-        self.0.get(name).and_then(|value| value.get())
-    }
+    /// The value marking a parameter for removal when merging.
+    pub const CLEAR: Clear = Clear;
 
-    /// Get a reference to the value of a parameter.
+    /// Create options.
     #[inline]
-    pub fn get_ref<T: Any>(&self, name: &str) -> Option<&T> {
-        self.0.get(name).and_then(|value| value.get_ref())
+    pub fn new() -> Options {
+        Options::with_hasher(RandomState::new())
     }
 
-    /// Get a mutable reference to the value of a parameter.
+    /// Create options validating names according to a policy.
     #[inline]
-    pub fn get_mut<T: Any>(&mut self, name: &str) -> Option<&mut T> {
-        self.0.get_mut(name).and_then(|value| value.get_mut())
+    pub fn with_key_policy(policy: KeyPolicy) -> Options {
+        Options {
+            policy,
+            ..Options::new()
+        }
     }
 
-    /// Set the value of a parameter.
+    /// Create options passing names through a normalizer.
+    ///
+    /// Every name given to `set`, `get`, and the other methods taking names is
+    /// normalized before the parameters are accessed, so that `names` yields
+    /// the normalized forms. The same applies to the names given to
+    /// `set_aliased`, `protect`, `set_secret`, and `canonicalize`. The
+    /// normalizer should be idempotent, such as one converting names to
+    /// lowercase or replacing dashes with underscores.
     #[inline]
-    pub fn set<T: Into<String>, U: Any>(&mut self, name: T, value: U) -> &mut Options {
-        self.0.insert(name.into(), Value(Box::new(value)));
-        self
+    pub fn with_key_normalizer(normalizer: fn(&str) -> String) -> Options {
+        Options {
+            normalizer: Some(normalizer),
+            ..Options::new()
+        }
     }
 
-    /// Check the presence of a parameter.
+    /// Create options recording the time of insertion of each parameter.
+    ///
+    /// The time is reported by `age_of` and used by `retain_newer_than`. It is
+    /// not recorded by default, since reading the clock on every `set` is
+    /// comparatively costly. Nested options are created without recording it
+    /// unless they are created via this function too.
     #[inline]
-    pub fn has(&self, name: &str) -> bool {
-        self.0.contains_key(name)
+    pub fn with_timestamps() -> Options {
+        Options {
+            timestamps: true,
+            ..Options::new()
+        }
     }
 
-    /// Return an iterator over parameters.
-    pub fn iter(&self) -> Parameters<'_> {
-        Parameters {
-            iterator: self.0.iter(),
+    /// Create options from a JSON object.
+    ///
+    /// Each member is stored as follows:
+    ///
+    /// * `null` is skipped;
+    /// * a boolean is stored as `bool`;
+    /// * an integer (see `Json`) is stored as `i64`;
+    /// * any other number is stored as `f64`;
+    /// * a string is stored as `String`;
+    /// * an object is stored as nested `Options`;
+    /// * a nonempty array of booleans, integers, or strings is stored as
+    ///   `Vec<bool>`, `Vec<i64>`, or `Vec<String>`, respectively;
+    /// * a nonempty array of numbers that are not all integers is stored as
+    ///   `Vec<f64>`; and
+    /// * any other array, including an empty one, is stored as `Vec<Json>`.
+    ///
+    /// If the value is not an object, the result is empty.
+    #[cfg(feature = "json")]
+    pub fn from_json(value: &Json) -> Options {
+        fn collect<T: Any, F: Fn(&Json) -> Option<T>>(values: &[Json], f: F) -> Option<Value> {
+            match values.is_empty() {
+                true => None,
+                _ => values
+                    .iter()
+                    .map(f)
+                    .collect::<Option<Vec<_>>>()
+                    .map(Value::new),
+            }
+        }
+        fn convert(value: &Json) -> Option<Value> {
+            Some(match value {
+                Json::Null => return None,
+                Json::Bool(value) => Value::new(*value),
+                Json::Integer(value) => Value::new(*value),
+                Json::Float(value) => Value::new(*value),
+                Json::String(value) => Value::new(value.clone()),
+                Json::Object(_) => Value::new(Options::from_json(value)),
+                Json::Array(values) => collect(values, |value| match value {
+                    Json::Bool(value) => Some(*value),
+                    _ => None,
+                })
+                .or_else(|| {
+                    collect(values, |value| match value {
+                        Json::Integer(value) => Some(*value),
+                        _ => None,
+                    })
+                })
+                .or_else(|| {
+                    collect(values, |value| match value {
+                        Json::String(value) => Some(value.clone()),
+                        _ => None,
+                    })
+                })
+                .or_else(|| {
+                    collect(values, |value| match value {
+                        Json::Integer(value) => Some(*value as f64),
+                        Json::Float(value) => Some(*value),
+                        _ => None,
+                    })
+                })
+                .unwrap_or_else(|| Value::new(values.clone())),
+            })
         }
+        let mut options = Options::new();
+        if let Json::Object(members) = value {
+            for (name, value) in members {
+                if let Some(value) = convert(value) {
+                    options.insert(Cow::Owned(name.clone()), value);
+                }
+            }
+        }
+        options
     }
 
-    /// Return an iterator over mutable parameters.
-    pub fn iter_mut(&mut self) -> ParametersMut<'_> {
-        ParametersMut {
-            iterator: self.0.iter_mut(),
+    /// Create options from a TOML document.
+    ///
+    /// Tables, including inline ones, are stored as nested `Options`, and the
+    /// other values are stored as in `from_json`, with arrays of tables stored
+    /// as `Vec<Json>`. Dates and times are stored as strings (`String`) in
+    /// their original form.
+    #[cfg(feature = "toml")]
+    pub fn from_toml_str(text: &str) -> Result<Options, TomlError> {
+        Ok(Options::from_json(&toml::parse(text)?))
+    }
+
+    /// Read options from lines of the form `name=value`.
+    ///
+    /// The names and values are read as strings (`String`) according to the
+    /// escaping rules of `write_properties`. Unescaped whitespace around names
+    /// and values is ignored, and so are blank lines and lines whose first
+    /// character other than whitespace is `#`, which are comments. A line that
+    /// is neither and has no unescaped equals sign results in an error of kind
+    /// `InvalidData`.
+    pub fn read_properties<R: std::io::BufRead>(reader: R) -> std::io::Result<Options> {
+        let mut options = Options::new();
+        for (i, line) in reader.lines().enumerate() {
+            match properties::read(&line?) {
+                Ok(Some((name, value))) => {
+                    options.set(name, value);
+                }
+                Ok(None) => {}
+                _ => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("line {} has no equals sign", i + 1),
+                    ))
+                }
+            }
         }
+        Ok(options)
     }
 
-    /// Return an iterator over names.
-    #[inline]
-    pub fn names(&self) -> Names<'_> {
-        fn first<'l>((name, _): (&'l Name, &'l Value)) -> &'l Name {
-            name
+    /// Create options by merging layers in order.
+    ///
+    /// The parameters of later layers overwrite those of earlier ones with the
+    /// same names, and cleared ones remove them (see `set_clear`). The values
+    /// are moved without cloning; see `merge_using` for other strategies.
+    pub fn from_layers<I: IntoIterator<Item = Options>>(layers: I) -> Options {
+        let mut options = Options::new();
+        for layer in layers {
+            for (name, value) in layer.parameters {
+                options.merge_one(name, value);
+            }
         }
-        Names {
-            iterator: self.0.iter().map(first),
+        options
+    }
+
+    /// Create options from name–value pairs of strings.
+    ///
+    /// The parameters are ordered as the pairs (see `get_at`), and their time
+    /// of insertion is left unknown (see `retain_newer_than`).
+    pub fn from_str_pairs<I: IntoIterator<Item = (String, String)>>(pairs: I) -> Options {
+        let mut options = Options::new();
+        for (name, value) in pairs {
+            options.version += 1;
+            let mut value = Value::new(value);
+            value.sequence = options.version;
+            value.modified = options.version;
+            options.parameters.insert(Cow::Owned(name), value);
         }
+        options
     }
 }
 
-impl Value {
-    /// Get the value.
+impl<S: BuildHasher> Options<S> {
+    /// Create options using a specific hash builder.
     #[inline]
-    pub fn get<T: Any + Clone>(&self) -> Option<T> {
-        self.0.downcast_ref::<T>().cloned()
+    pub fn with_hasher(hasher: S) -> Options<S> {
+        Options {
+            parameters: HashMap::with_hasher(hasher),
+            aliases: HashMap::new(),
+            protected: HashSet::new(),
+            secrets: HashSet::new(),
+            policy: KeyPolicy::default(),
+            normalizer: None,
+            traits: HashMap::new(),
+            factories: HashMap::new(),
+            checkpoints: Vec::new(),
+            version: 0,
+            sorted_names: None,
+            timestamps: false,
+            warning_hook: None,
+        }
     }
 
-    /// Get a reference to the value.
+    /// Get the value of a parameter.
     #[inline]
-    pub fn get_ref<T: Any>(&self) -> Option<&T> {
-        self.0.downcast_ref::<T>()
+    pub fn get<T: Any + Clone>(&self, name: &str) -> Option<T> {
+        self.live(name).and_then(|value| value.get())
     }
 
-    /// Get a mutable reference to the value.
-    #[inline]
-    pub fn get_mut<T: Any>(&mut self) -> Option<&mut T> {
-        self.0.downcast_mut::<T>()
+    /// Get the value of a parameter warning about a type mismatch.
+    ///
+    /// The method behaves like `get`, but, if the parameter is present with a
+    /// value of a different type, a warning naming the parameter, the expected
+    /// type, and the actual type is emitted via the hook given to
+    /// `set_warning_hook`.
+    pub fn checked_get<T: Any + Clone>(&self, name: &str) -> Option<T> {
+        let value = self.live(name)?;
+        let result = value.get();
+        if result.is_none() {
+            warn(
+                self.warning_hook.as_ref(),
+                &format!(
+                    "the parameter {name:?} is of type {}, not {}",
+                    value.type_name,
+                    std::any::type_name::<T>(),
+                ),
+            );
+        }
+        result
     }
 
-    /// Set the value.
-    #[inline]
-    pub fn set<T: Any>(&mut self, value: T) {
-        self.0 = Box::new(value);
+    /// Get the value of a parameter or a default warning about the latter.
+    ///
+    /// If the parameter is absent or has a value of a different type, a warning
+    /// naming the parameter and the reason is emitted via the hook given to
+    /// `set_warning_hook`, and the default is returned.
+    pub fn get_or_warn<T: Any + Clone>(&self, name: &str, default: T) -> T {
+        let message = match self.live(name) {
+            Some(value) => match value.get() {
+                Some(value) => return value,
+                _ => format!(
+                    "the parameter {name:?} is of type {}, not {}, and the default is used",
+                    value.type_name,
+                    std::any::type_name::<T>(),
+                ),
+            },
+            _ => format!("the parameter {name:?} is missing, and the default is used"),
+        };
+        warn(self.warning_hook.as_ref(), &message);
+        default
     }
-}
 
-impl<'l> IntoIterator for &'l Options {
-    type Item = (&'l Name, &'l Value);
-    type IntoIter = Parameters<'l>;
+    /// Get the value of a parameter that is a string or of an environment
+    /// variable.
+    ///
+    /// The value of the parameter should be a `String` or a `&'static str`, and
+    /// values of other types are treated as absent. If the parameter is absent,
+    /// the environment variable is read, and `None` is returned if it is not set
+    /// or is not valid Unicode.
+    pub fn get_or_env(&self, name: &str, variable: &str) -> Option<String> {
+        match self.get_str(name) {
+            Some(value) => Some(value.to_string()),
+            _ => std::env::var(variable).ok(),
+        }
+    }
 
+    /// Get the value of a parameter that is an array.
+    ///
+    /// If the stored array has a different length, `None` is returned.
     #[inline]
-    fn into_iter(self) -> Parameters<'l> {
-        self.iter()
+    pub fn get_array<T: Any + Copy, const N: usize>(&self, name: &str) -> Option<[T; N]> {
+        self.get_ref::<[T; N]>(name).copied()
     }
-}
 
-impl<'l> IntoIterator for &'l mut Options {
-    type Item = (&'l Name, &'l mut Value);
-    type IntoIter = ParametersMut<'l>;
+    /// Get the value of a parameter that lies within a range.
+    ///
+    /// If the value is below `min` or above `max`, `None` is returned. Both
+    /// bounds are inclusive.
+    pub fn get_in_range<T: Any + Clone + PartialOrd>(
+        &self,
+        name: &str,
+        min: T,
+        max: T,
+    ) -> Option<T> {
+        self.get_ref::<T>(name)
+            .filter(|value| min <= **value && **value <= max)
+            .cloned()
+    }
 
+    /// Get the value of a parameter that is a range.
     #[inline]
-    fn into_iter(self) -> ParametersMut<'l> {
-        self.iter_mut()
+    pub fn get_range<T: Any + Clone>(&self, name: &str) -> Option<Range<T>> {
+        self.get(name)
     }
-}
 
-impl<'l> Iterator for Parameters<'l> {
-    type Item = (&'l Name, &'l Value);
+    /// Get the union of the values of several parameters that are flags.
+    ///
+    /// The values of type `T` among the given parameters are combined via `|`,
+    /// and the absent parameters and values of other types are skipped. If no
+    /// value is found, `None` is returned.
+    pub fn get_flags<T>(&self, names: &[&str]) -> Option<T>
+    where
+        T: Any + Clone + BitOr<Output = T>,
+    {
+        names
+            .iter()
+            .filter_map(|name| self.get::<T>(name))
+            .reduce(|one, other| one | other)
+    }
+
+    /// Get the values of all parameters of a specific type.
+    pub fn collect_type<T: Any + Clone>(&self) -> Vec<T> {
+        self.parameters.values().filter_map(Value::get).collect()
+    }
+
+    /// Get the value of a parameter that is a duration.
+    ///
+    /// The value can be either a `Duration` or a string, which is a `String` or
+    /// a `&'static str`. A string should be a non-negative integer or decimal
+    /// number immediately followed by one of the following units: `ns`, `us`
+    /// (or `µs`), `ms`, `s`, `m`, and `h`. For instance, `"500ms"` and `"1.5s"`
+    /// are accepted. Surrounding whitespace is ignored.
+    pub fn get_duration(&self, name: &str) -> Option<Duration> {
+        match self.get_ref::<Duration>(name) {
+            Some(value) => Some(*value),
+            _ => parse::duration(self.get_str(name)?),
+        }
+    }
+
+    /// Get the value of a parameter that is a sequence of bytes.
+    ///
+    /// The value can be either a `Vec<u8>` or a string, which is a `String` or a
+    /// `&'static str`, with each byte written as two hexadecimal digits in
+    /// either case, such as `"0aff"`. Surrounding whitespace is ignored. If the
+    /// string is malformed, `None` is returned.
+    pub fn get_bytes(&self, name: &str) -> Option<Vec<u8>> {
+        match self.get(name) {
+            Some(value) => Some(value),
+            _ => parse::hex(self.get_str(name)?),
+        }
+    }
 
+    /// Get the value of a parameter that is a socket address.
+    ///
+    /// The value can be either a `SocketAddr` or a string, which is a `String`
+    /// or a `&'static str`, such as `"127.0.0.1:8080"`.
     #[inline]
-    fn next(&mut self) -> Option<(&'l Name, &'l Value)> {
-        self.iterator.next()
+    pub fn get_socket_addr(&self, name: &str) -> Option<SocketAddr> {
+        self.get_or_parse(name)
     }
-}
 
-impl<'l> Iterator for ParametersMut<'l> {
-    type Item = (&'l Name, &'l mut Value);
+    /// Get the value of a parameter that is an IP address.
+    ///
+    /// The value can be either an `IpAddr` or a string, which is a `String` or
+    /// a `&'static str`, such as `"127.0.0.1"` or `"::1"`.
+    #[inline]
+    pub fn get_ip_addr(&self, name: &str) -> Option<IpAddr> {
+        self.get_or_parse(name)
+    }
+
+    /// Get the value of a parameter that is a list of IP addresses.
+    ///
+    /// The value can be either a `Vec<IpAddr>` or a string, which is a `String`
+    /// or a `&'static str`, with addresses separated by commas, such as
+    /// `"1.1.1.1, 8.8.8.8"`. The addresses are trimmed of surrounding
+    /// whitespace, and empty ones are skipped. If any address is malformed,
+    /// `None` is returned.
+    pub fn get_ip_list(&self, name: &str) -> Option<Vec<IpAddr>> {
+        match self.get(name) {
+            Some(value) => Some(value),
+            _ => self
+                .get_str(name)?
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(|item| item.parse().ok())
+                .collect(),
+        }
+    }
 
+    /// Get the value of a parameter that is a character.
+    ///
+    /// The value can be either a `char` or a string, which is a `String` or a
+    /// `&'static str`, consisting of exactly one character.
+    pub fn get_char(&self, name: &str) -> Option<char> {
+        match self.get_ref::<char>(name) {
+            Some(value) => Some(*value),
+            _ => {
+                let mut characters = self.get_str(name)?.chars();
+                match (characters.next(), characters.next()) {
+                    (Some(character), None) => Some(character),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// Get the value of a parameter that is a nonzero integer.
+    ///
+    /// `T` is a nonzero integer type, such as `NonZeroU32`, and the value should
+    /// be of the corresponding integer type, such as `u32`. If the value is zero,
+    /// `None` is returned.
     #[inline]
-    fn next(&mut self) -> Option<(&'l Name, &'l mut Value)> {
-        self.iterator.next()
+    pub fn get_nonzero<T: Any + Copy>(&self, name: &str) -> Option<T> {
+        types::nonzero(self.live(name)?.as_any())
     }
-}
 
-impl<'l> Iterator for Names<'l> {
-    type Item = &'l Name;
+    /// Get the value of a parameter that is a list of strings.
+    ///
+    /// The value can be either a `Vec<String>` or a string, which is a `String`
+    /// or a `&'static str`, with items separated by `delimiter`. In the latter
+    /// case, the items are trimmed of surrounding whitespace, and empty ones are
+    /// skipped; for instance, `"a, b,,c "` gives `["a", "b", "c"]`.
+    pub fn get_list(&self, name: &str, delimiter: char) -> Option<Vec<String>> {
+        match self.get(name) {
+            Some(value) => Some(value),
+            _ => Some(
+                self.get_str(name)?
+                    .split(delimiter)
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(String::from)
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Get the value of a parameter ignoring the ASCII case of the name.
+    ///
+    /// Unlike `get`, which is a constant-time lookup, this method scans all the
+    /// names when there is no exact match. If several names match, the value of
+    /// an arbitrary one is returned.
+    pub fn get_fold<T: Any + Clone>(&self, name: &str) -> Option<T> {
+        match self.live(name) {
+            Some(value) => value.get(),
+            _ => self
+                .parameters
+                .iter()
+                .find(|(other, value)| !value.expired() && other.eq_ignore_ascii_case(name))
+                .and_then(|(_, value)| value.get()),
+        }
+    }
 
+    /// Get the value of a nested parameter.
+    ///
+    /// The path is a sequence of names separated by dots, where a dot within a
+    /// name is escaped with a backslash (see `path`). All the names but the last
+    /// one should refer to parameters whose values are options.
     #[inline]
-    fn next(&mut self) -> Option<&'l Name> {
-        self.iterator.next()
+    pub fn get_path<T: Any + Clone>(&self, path: &str) -> Option<T> {
+        self.get_path_ref(path).cloned()
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::Options;
+    /// Get the value of a nested parameter identified by a typed path.
+    #[inline]
+    pub fn get_path_keyed<T: Any + Clone>(&self, key: PathKey<T>) -> Option<T> {
+        self.get_path(key.path)
+    }
 
-    #[test]
-    fn get() {
-        let options = setup();
+    /// Get the value of a nested parameter or a default.
+    ///
+    /// The default is returned when the parameter is absent or has a value of a
+    /// different type.
+    #[inline]
+    pub fn get_path_or<T: Any + Clone>(&self, path: &str, default: T) -> T {
+        self.get_path(path).unwrap_or(default)
+    }
 
-        macro_rules! test(
-            ($name:expr, $value:expr, $kind:ty) => (
-                assert_eq!(options.get::<$kind>($name).unwrap(), $value)
-            );
-        );
+    /// Get the values of nested parameters matching a pattern.
+    ///
+    /// The pattern is a path (see `get_path`) where a name can be replaced with
+    /// `*` to match any name. The values are returned together with their paths,
+    /// whose names are escaped, in an unspecified order.
+    pub fn get_path_all<T: Any + Clone>(&self, pattern: &str) -> Vec<(String, T)> {
+        let segments = path::parse_path_escaped(pattern, '.');
+        let segments = segments.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+        let mut values = Vec::new();
+        self.collect_path("", &segments, &mut values);
+        values
+    }
 
-        test!("a", 42, i32);
-        test!("b", true, bool);
-        test!("c", "Hi, there!", &str);
-        test!("d", "Hello, world!".to_string(), String);
-        test!("e", vec![4u8, 2u8], Vec<u8>);
+    /// Get a reference to the value of a nested parameter.
+    pub fn get_path_ref<T: Any>(&self, path: &str) -> Option<&T> {
+        match path::split_last(path, '.') {
+            (Some(parent), name) => self.get_path_ref::<Options>(parent)?.get_ref(&name),
+            (_, name) => self.get_ref(&name),
+        }
     }
 
-    #[test]
-    fn get_ref() {
-        let options = setup();
+    /// Get the value of a nested parameter borrowing it if possible.
+    ///
+    /// If the value is of type `T`, it is borrowed. Otherwise, if it is a
+    /// string, which is a `String` or a `&'static str`, parsing as `T`, the
+    /// parsed value is returned as owned.
+    pub fn path_cow<T: Any + Clone + FromStr>(&self, path: &str) -> Option<Cow<'_, T>> {
+        match path::split_last(path, '.') {
+            (Some(parent), name) => self.get_path_ref::<Options>(parent)?.cow(&name),
+            (_, name) => self.cow(&name),
+        }
+    }
 
-        macro_rules! test(
-            ($name:expr, $value:expr, $kind:ty) => (
-                assert_eq!(options.get_ref::<$kind>($name).unwrap(), $value)
-            );
-        );
+    /// Get the value of a nested parameter that is a string.
+    ///
+    /// The value can be a `String`, a `&'static str`, or a `Cow<'static, str>`.
+    pub fn path_str(&self, path: &str) -> Option<&str> {
+        match path::split_last(path, '.') {
+            (Some(parent), name) => self.get_path_ref::<Options>(parent)?.get_text(&name),
+            (_, name) => self.get_text(&name),
+        }
+    }
 
-        test!("a", &42, i32);
-        test!("b", &true, bool);
-        test!("c", &"Hi, there!", &str);
-        test!("d", "Hello, world!", String);
-        test!("e", &vec![4u8, 2u8], Vec<u8>);
+    /// Check if a nested parameter is a flag that is set.
+    ///
+    /// The result is true only if the parameter is present and its value is
+    /// `true`; an absent parameter or a value of a different type means false.
+    #[inline]
+    pub fn path_flag(&self, path: &str) -> bool {
+        self.get_path_ref::<bool>(path).copied().unwrap_or(false)
     }
 
-    #[test]
-    fn get_mut() {
-        let mut options = setup();
+    /// Get the value of a nested parameter that is a string or of an
+    /// environment variable.
+    ///
+    /// The value of the parameter should be a string as in `path_str`, and
+    /// values of other types are treated as absent. The environment variable is
+    /// treated as in `get_or_env`.
+    pub fn path_or_env(&self, path: &str, variable: &str) -> Option<String> {
+        match self.path_str(path) {
+            Some(value) => Some(value.to_string()),
+            _ => std::env::var(variable).ok(),
+        }
+    }
 
-        macro_rules! test(
-            ($name:expr, $value:expr, $kind:ty) => ({
-                *options.get_mut::<$kind>($name).unwrap() = $value;
-                assert_eq!(options.get::<$kind>($name).unwrap(), $value);
-            });
-        );
+    /// Get a type-erased reference to the value of a nested parameter.
+    pub fn path_any(&self, path: &str) -> Option<&dyn Any> {
+        match path::split_last(path, '.') {
+            (Some(parent), name) => self.get_path_ref::<Options>(parent)?.get_any(&name),
+            (_, name) => self.get_any(&name),
+        }
+    }
 
-        test!("a", 24, i32);
-        test!("b", false, bool);
-        test!("c", "Hi, here!", &str);
-        test!("d", "Bye, world!".to_string(), String);
-        test!("e", vec![2u8, 4u8], Vec<u8>);
+    /// Get the value of a nested parameter that is a list of strings.
+    ///
+    /// The value is treated as in `get_list`.
+    pub fn path_list(&self, path: &str, delimiter: char) -> Option<Vec<String>> {
+        match path::split_last(path, '.') {
+            (Some(parent), name) => self
+                .get_path_ref::<Options>(parent)?
+                .get_list(&name, delimiter),
+            (_, name) => self.get_list(&name, delimiter),
+        }
     }
 
-    #[test]
-    fn iter_mut() {
-        let mut options = setup();
-        for (_, value) in &mut options {
-            value.set(69);
+    /// Render the value of a nested parameter as a string.
+    ///
+    /// Only the values of registered types (see `bytes_estimate`) are rendered,
+    /// in the same manner as in `display_map`.
+    pub fn path_display(&self, path: &str) -> Option<String> {
+        match path::split_last(path, '.') {
+            (Some(parent), name) => self.get_path_ref::<Options>(parent)?.live(&name)?.display(),
+            (_, name) => self.live(&name)?.display(),
         }
+    }
 
-        macro_rules! test(
-            ($name:expr) => (assert_eq!(options.get_ref::<i32>($name).unwrap(), &69));
-        );
+    /// Check the presence of a nested parameter.
+    ///
+    /// All the names in the path but the last one should refer to options, and
+    /// the value of the last one can be of any type.
+    pub fn has_path(&self, path: &str) -> bool {
+        match path::split_last(path, '.') {
+            (Some(parent), name) => self
+                .get_path_ref::<Options>(parent)
+                .is_some_and(|options| options.has(&name)),
+            (_, name) => self.has(&name),
+        }
+    }
 
-        test!("a");
-        test!("b");
-        test!("c");
-        test!("d");
-        test!("e");
+    /// Return an iterator over the names of the nested options at a path.
+    ///
+    /// If the path does not lead to options, `None` is returned.
+    #[inline]
+    pub fn path_children(&self, path: &str) -> Option<Names<'_>> {
+        self.get_path_ref::<Options>(path).map(Options::names)
     }
 
-    #[test]
-    fn has() {
-        let options = setup();
-        assert_eq!(options.has("a"), true);
-        assert_eq!(options.has("z"), false);
+    /// Get the values of a specific type in the nested options at a path.
+    ///
+    /// The values are returned together with their names in the nested options
+    /// in an unspecified order. If the path does not lead to options, the
+    /// result is empty.
+    pub fn subtree_values<T: Any + Clone>(&self, path: &str) -> Vec<(String, T)> {
+        match self.get_path_ref::<Options>(path) {
+            Some(options) => options
+                .parameters
+                .iter()
+                .filter_map(|(name, value)| Some((name.to_string(), value.get()?)))
+                .collect(),
+            _ => Vec::new(),
+        }
     }
 
-    #[test]
-    fn names() {
-        let options = setup();
-        let mut names = options.names().collect::<Vec<_>>();
-        names.sort();
-        assert_eq!(names, &["a", "b", "c", "d", "e"]);
+    /// Get the values of a specific type in the nested options at a path as a
+    /// map.
+    ///
+    /// The values are keyed by their names in the nested options. If the path
+    /// does not lead to options, `None` is returned.
+    pub fn subtree_map<T: Any + Clone>(&self, path: &str) -> Option<HashMap<String, T>> {
+        let options = self.get_path_ref::<Options>(path)?;
+        Some(
+            options
+                .parameters
+                .iter()
+                .filter_map(|(name, value)| Some((name.to_string(), value.get()?)))
+                .collect(),
+        )
     }
 
-    fn setup() -> Options {
-        let mut options = Options::default();
+    /// Get a mutable reference to the value of a nested parameter.
+    pub fn get_path_mut<T: Any>(&mut self, path: &str) -> Option<&mut T> {
+        match path::split_last(path, '.') {
+            (Some(parent), name) => self.get_path_mut::<Options>(parent)?.get_mut(&name),
+            (_, name) => self.get_mut(&name),
+        }
+    }
 
-        options
-            .set("a", 42)
-            .set("b", true)
+    /// Get a mutable reference to the value of a nested parameter inserting a
+    /// default first.
+    ///
+    /// The nested options leading to the parameter are created when they are
+    /// absent or replace the values of other types. The default is inserted
+    /// when the parameter is absent or has a value of a different type. Both
+    /// happen even if the names involved are protected (see `protect`).
+    #[inline]
+    pub fn path_entry_or_insert<T: Any + Default>(&mut self, path: &str) -> &mut T {
+        self.path_entry_or_insert_with(path, T::default)
+    }
+
+    /// Get the value of a nested parameter inserting a computed default first.
+    ///
+    /// The function is called only if the default is needed, and the nested
+    /// options are treated as in `path_entry_or_insert`.
+    #[inline]
+    pub fn path_or_insert_with<T, F>(&mut self, path: &str, initialize: F) -> T
+    where
+        T: Any + Clone,
+        F: FnOnce() -> T,
+    {
+        self.path_entry_or_insert_with(path, initialize).clone()
+    }
+
+    /// Return an entry of a nested parameter for in-place manipulation.
+    ///
+    /// The nested options leading to the parameter are created only when a
+    /// value is inserted; see `PathEntry`.
+    #[inline]
+    pub fn path_entry<'l>(&'l mut self, path: &'l str) -> PathEntry<'l, S> {
+        PathEntry {
+            options: self,
+            path,
+        }
+    }
+
+    /// Remove a nested parameter and return its value.
+    ///
+    /// The value is moved out without cloning. If it is of a different type,
+    /// nothing is removed. The nested options left empty by the removal are
+    /// removed as well.
+    pub fn take_path<T: Any>(&mut self, path: &str) -> Option<T> {
+        let (name, rest) = match path::split_first(path, '.') {
+            (name, Some(rest)) => (name, rest),
+            (name, _) => {
+                self.get_ref::<T>(&name)?;
+                return self.remove(&name)?.1.downcast().ok();
+            }
+        };
+        let options = self.get_mut::<Options>(&name)?;
+        let value = options.take_path(rest)?;
+        if options.parameters.is_empty() {
+            self.delete(&name);
+        }
+        Some(value)
+    }
+
+    /// Get the values of two parameters if both are present.
+    #[inline]
+    pub fn get_tuple2<A, B>(&self, a: &str, b: &str) -> Option<(A, B)>
+    where
+        A: Any + Clone,
+        B: Any + Clone,
+    {
+        Some((self.get(a)?, self.get(b)?))
+    }
+
+    /// Get the values of three parameters if all are present.
+    #[inline]
+    pub fn get_tuple3<A, B, C>(&self, a: &str, b: &str, c: &str) -> Option<(A, B, C)>
+    where
+        A: Any + Clone,
+        B: Any + Clone,
+        C: Any + Clone,
+    {
+        Some((self.get(a)?, self.get(b)?, self.get(c)?))
+    }
+
+    /// Get the values of four parameters if all are present.
+    #[inline]
+    pub fn get_tuple4<A, B, C, D>(&self, a: &str, b: &str, c: &str, d: &str) -> Option<(A, B, C, D)>
+    where
+        A: Any + Clone,
+        B: Any + Clone,
+        C: Any + Clone,
+        D: Any + Clone,
+    {
+        Some((self.get(a)?, self.get(b)?, self.get(c)?, self.get(d)?))
+    }
+
+    /// Get a reference to the value of a parameter.
+    #[inline]
+    pub fn get_ref<T: Any>(&self, name: &str) -> Option<&T> {
+        self.live(name).and_then(|value| value.get_ref())
+    }
+
+    /// Get a reference to the value of a parameter or to a fallback.
+    ///
+    /// The function is called only if the parameter is absent or has a value
+    /// of a different type.
+    #[inline]
+    pub fn get_ref_or_else<'l, T, F>(&'l self, name: &str, f: F) -> &'l T
+    where
+        T: Any,
+        F: FnOnce() -> &'l T,
+    {
+        match self.get_ref(name) {
+            Some(value) => value,
+            _ => f(),
+        }
+    }
+
+    /// Get a reference to the value of a parameter or an error explaining why
+    /// it is unavailable.
+    pub fn try_get_ref<T: Any>(&self, name: &str) -> Result<&T, GetError> {
+        let value = self.live(name).ok_or(GetError::Missing)?;
+        value.get_ref().ok_or(GetError::Mismatch {
+            expected: std::any::type_name::<T>(),
+            actual: value.type_name,
+        })
+    }
+
+    /// Get the value of a parameter and convert it via a function.
+    pub fn get_converted<T, U, F, E>(&self, name: &str, convert: F) -> Result<U, ConvertError<E>>
+    where
+        T: Any + Clone,
+        F: FnOnce(T) -> Result<U, E>,
+    {
+        let value = self.try_get_ref::<T>(name).map_err(ConvertError::Get)?;
+        convert(value.clone()).map_err(ConvertError::Convert)
+    }
+
+    /// Get a type-erased reference to the value of a parameter.
+    #[inline]
+    pub fn get_any(&self, name: &str) -> Option<&dyn Any> {
+        self.live(name).map(Value::as_any)
+    }
+
+    /// Get a type-erased mutable reference to the value of a parameter.
+    #[inline]
+    pub fn get_any_mut(&mut self, name: &str) -> Option<&mut dyn Any> {
+        self.value_mut(name).map(Value::as_any_mut)
+    }
+
+    /// Clone the value of a parameter.
+    ///
+    /// See `Value::try_clone` for the values that can be cloned.
+    #[inline]
+    pub fn clone_value(&self, name: &str) -> Option<Value> {
+        self.live(name)?.try_clone()
+    }
+
+    /// Get a reference to the value of a parameter as a trait object.
+    ///
+    /// The conversion should have been registered via `register_trait` for the
+    /// type of the value and the trait.
+    pub fn get_trait_ref<T: ?Sized + 'static>(&self, name: &str) -> Option<&T> {
+        let value = self.live(name)?;
+        let caster = self.traits.get(&(value.type_id, TypeId::of::<T>()))?;
+        caster.downcast_ref::<Caster<T>>()?(value.as_any())
+    }
+
+    /// Register a conversion of values of type `T` into trait objects `U`.
+    ///
+    /// Since a `dyn Any` cannot be converted into an arbitrary trait object, the
+    /// conversion has to be provided, which is usually an identity closure:
+    ///
+    /// ```
+    /// # use options::Options;
+    /// use std::fmt::Display;
+    ///
+    /// let mut options = Options::new();
+    /// options.register_trait::<i32, dyn Display>(|value| value);
+    /// options.set("a", 42);
+    ///
+    /// assert_eq!(options.get_trait_ref::<dyn Display>("a").unwrap().to_string(), "42");
+    /// ```
+    pub fn register_trait<T: Any, U: ?Sized + 'static>(&mut self, convert: fn(&T) -> &U) {
+        let caster: Caster<U> = Box::new(move |value| value.downcast_ref::<T>().map(convert));
+        self.traits
+            .insert((TypeId::of::<T>(), TypeId::of::<U>()), Rc::new(caster));
+    }
+
+    /// Get a reference to the value of a parameter inserting a default first.
+    ///
+    /// The default is inserted when the parameter is absent or has a value of a
+    /// different type, even if the parameter is protected (see `protect`).
+    pub fn get_or_insert_ref<T: Any>(&mut self, name: &str, default: T) -> &T {
+        if self.get_ref::<T>(name).is_none() {
+            let name = self.canonical(Cow::Owned(name.to_string()));
+            self.overwrite(name, Value::new(default));
+        }
+        match self.get_ref(name) {
+            Some(value) => value,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Get the value of a parameter inserting a default produced by a factory
+    /// first.
+    ///
+    /// The default is produced by the factory registered via `register_default`
+    /// for type `T` and inserted when the parameter is absent or has a value of
+    /// a different type. If no factory is registered, `None` is returned. If
+    /// the parameter is protected (see `protect`), the default is returned
+    /// without being inserted, and the factory is called again next time.
+    pub fn get_or_factory<T: Any + Clone>(&mut self, name: &str) -> Option<T> {
+        if let Some(value) = self.get(name) {
+            return Some(value);
+        }
+        let factory = self.factories.get(&TypeId::of::<T>())?;
+        let value = factory.downcast_ref::<fn() -> T>().unwrap()();
+        self.set(name, value.clone());
+        Some(value)
+    }
+
+    /// Set the function receiving warnings.
+    ///
+    /// Warnings are emitted by methods such as `checked_get`. By default, they
+    /// are discarded. A hook can forward them to a logging facility of choice,
+    /// such as `log` or `tracing`, along with any context it captures. The hook
+    /// belongs to these options: it is copied into the options derived from
+    /// them, as by `partition`, but not into nested options, except that
+    /// `into_shared` uses it for the nested options without a hook of their own.
+    pub fn set_warning_hook<F: Fn(&str) + 'static>(&mut self, hook: F) {
+        self.warning_hook = Some(WarningHook(Rc::new(hook)));
+    }
+
+    /// Remove the function receiving warnings.
+    #[inline]
+    pub fn clear_warning_hook(&mut self) {
+        self.warning_hook = None;
+    }
+
+    /// Register a factory producing defaults of type `T`.
+    ///
+    /// The factory is used by `get_or_factory` and replaces the one registered
+    /// before for the same type.
+    #[inline]
+    pub fn register_default<T: Any>(&mut self, factory: fn() -> T) {
+        self.factories.insert(TypeId::of::<T>(), Rc::new(factory));
+    }
+
+    /// Get a mutable reference to the value of a parameter.
+    #[inline]
+    pub fn get_mut<T: Any>(&mut self, name: &str) -> Option<&mut T> {
+        let canonical = self.normalize(name);
+        match self.parameters.get(resolve(&self.aliases, &canonical)) {
+            Some(value) if value.expired() => {
+                self.delete(name);
+                None
+            }
+            Some(value) if value.get_ref::<T>().is_some() => {
+                self.value_mut(name).and_then(|value| value.get_mut())
+            }
+            _ => None,
+        }
+    }
+
+    /// Set the value of a parameter.
+    #[inline]
+    pub fn set<T: Into<String>, U: Any>(&mut self, name: T, value: U) -> &mut Options<S> {
+        self.insert(Cow::Owned(name.into()), Value::new(value));
+        self
+    }
+
+    /// Set the value of a parameter that has aliases.
+    ///
+    /// The value is stored under the canonical name, and the aliases are
+    /// recorded so that reading a parameter via `get`, `get_ref`, and the like,
+    /// writing it via `set`, `get_mut`, and the like, and removing it via
+    /// `remove_value` and the like with an alias are redirected to the
+    /// canonical name. The parameters already present under the aliases are
+    /// removed.
+    pub fn set_aliased<T: Any>(&mut self, canonical: &str, aliases: &[&str], value: T) {
+        let canonical = self.normalize(canonical).into_owned();
+        for alias in aliases {
+            let alias = self.normalize(alias).into_owned();
+            self.aliases.remove(alias.as_str());
+            self.delete(&alias);
+            self.aliases
+                .insert(Cow::Owned(alias), Cow::Owned(canonical.clone()));
+        }
+        self.set(canonical, value);
+    }
+
+    /// Set the value of a parameter if it is given.
+    ///
+    /// If the value is `None`, the parameter is neither inserted nor removed.
+    #[inline]
+    pub fn set_optional<U: Any>(&mut self, name: &str, value: Option<U>) -> &mut Options<S> {
+        if let Some(value) = value {
+            self.set(name, value);
+        }
+        self
+    }
+
+    /// Set the value of a parameter unless it is already equal to the given one
+    /// and return whether the parameter has changed.
+    ///
+    /// If nothing has changed, the version is left as it is.
+    pub fn set_if_changed<T: Any + PartialEq>(&mut self, name: &str, value: T) -> bool {
+        if self.get_ref::<T>(name) == Some(&value) {
+            return false;
+        }
+        self.set(name, value);
+        true
+    }
+
+    /// Update the value of a parameter or insert a default.
+    ///
+    /// If the parameter has a value of type `T`, the update is applied to it.
+    /// Otherwise, the default is inserted, and the update is not applied.
+    pub fn update_or_insert<T, F>(&mut self, name: &str, default: T, update: F) -> &mut Options<S>
+    where
+        T: Any + Clone,
+        F: FnOnce(&mut T),
+    {
+        match self.get_mut::<T>(name) {
+            Some(value) => update(value),
+            _ => {
+                self.set(name, default);
+            }
+        }
+        self
+    }
+
+    /// Set the value of a parameter given as a type-erased value.
+    #[inline]
+    pub fn set_value<T: Into<String>>(&mut self, name: T, value: Value) -> &mut Options<S> {
+        self.insert(Cow::Owned(name.into()), value);
+        self
+    }
+
+    /// Remove a parameter and return its type-erased value.
+    #[inline]
+    pub fn remove_value(&mut self, name: &str) -> Option<Value> {
+        self.remove(name).map(|(_, value)| value)
+    }
+
+    /// Get the value of a parameter by either cloning or removing it.
+    ///
+    /// If `take` is false, the value is cloned, and the parameter is left in
+    /// place. If `take` is true, the parameter is removed, and the value is
+    /// moved out without cloning. In both cases, nothing happens if the value
+    /// is of a different type.
+    pub fn get_or_take<T: Any + Clone>(&mut self, name: &str, take: bool) -> Option<T> {
+        if !take {
+            return self.get(name);
+        }
+        self.get_ref::<T>(name)?;
+        self.remove(name)?.1.downcast().ok()
+    }
+
+    /// Remove all parameters and return them.
+    ///
+    /// The protected parameters are left in place as with `drain`.
+    #[inline]
+    pub fn take_all(&mut self) -> Vec<(Name, Value)> {
+        self.drain().collect()
+    }
+
+    /// Convert into the parameters sorted by name.
+    ///
+    /// The values are moved without cloning.
+    pub fn into_sorted_vec(self) -> Vec<(Name, Value)> {
+        let mut parameters = self.parameters.into_iter().collect::<Vec<_>>();
+        parameters.sort_unstable_by(|one, other| one.0.cmp(&other.0));
+        parameters
+    }
+
+    /// Remove all parameters.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.remove_where(|_, _| true);
+    }
+
+    /// Set the values of parameters given as boxed values.
+    pub fn set_many_boxed<I, N>(&mut self, parameters: I) -> &mut Options<S>
+    where
+        I: IntoIterator<Item = (N, Box<dyn Any>)>,
+        N: Into<Name>,
+    {
+        for (name, value) in parameters {
+            self.insert(name.into(), Value::from_boxed(value));
+        }
+        self
+    }
+
+    /// Move all parameters of other options prefixing their names.
+    pub fn import(&mut self, prefix: &str, other: Options<S>) {
+        for (name, value) in other.parameters {
+            self.insert(Cow::Owned(format!("{prefix}{name}")), value);
+        }
+    }
+
+    /// Apply overrides of the form `name=value`.
+    ///
+    /// The values are set as `String`s, and the names are paths (see
+    /// `get_path`) with the nested options treated as in
+    /// `path_entry_or_insert`. The name is everything before the first equals
+    /// sign and should not be empty. If any override is malformed, nothing is
+    /// applied. The overrides whose paths pass through or end at protected
+    /// names (see `protect`) are skipped. The number of applied overrides is
+    /// returned.
+    pub fn apply_overrides<I>(&mut self, overrides: I) -> Result<usize, ParseError>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let overrides = overrides
+            .into_iter()
+            .enumerate()
+            .map(|(index, value)| match value.split_once('=') {
+                Some((name, value)) if !name.is_empty() => {
+                    Ok((name.to_string(), value.to_string()))
+                }
+                _ => Err(ParseError { index }),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut count = 0;
+        for (name, value) in overrides {
+            if !self.writable(&name) {
+                continue;
+            }
+            count += 1;
+            match path::split_last(&name, '.') {
+                (Some(parent), name) => {
+                    self.path_entry_or_insert::<Options>(parent)
+                        .set(name, value);
+                }
+                (_, name) => {
+                    self.set(name, value);
+                }
+            }
+        }
+        Ok(count)
+    }
+
+    /// Merge other options unless the types of values differ.
+    ///
+    /// The parameters of `other` overwrite those of `self` with the same names
+    /// as long as the types of their values coincide. Otherwise, the existing
+    /// values are left untouched, and the conflicts are returned after all the
+    /// other parameters have been merged.
+    pub fn try_merge(&mut self, other: Options<S>) -> Result<(), Vec<Conflict>> {
+        let mut conflicts = Vec::new();
+        for (name, value) in other.parameters {
+            match self.live(&name) {
+                _ if value.type_id == TypeId::of::<Clear>() => {
+                    self.delete(&name);
+                }
+                Some(existing) if existing.type_id != value.type_id => {
+                    conflicts.push(Conflict {
+                        name,
+                        existing_type: existing.type_name,
+                        incoming_type: value.type_name,
+                    });
+                }
+                _ => {
+                    self.insert(name, value);
+                }
+            }
+        }
+        if conflicts.is_empty() {
+            Ok(())
+        } else {
+            Err(conflicts)
+        }
+    }
+
+    /// Merge other options recursively.
+    ///
+    /// When both values with the same name are options, they are merged in the
+    /// same manner. Otherwise, the parameters of `other` overwrite those of
+    /// `self` with the same names.
+    pub fn deep_merge(&mut self, other: Options<S>) {
+        for (name, value) in other.parameters {
+            let value = match value.downcast::<Options>() {
+                Ok(value) => match self.get_mut::<Options>(&name) {
+                    Some(existing) => {
+                        existing.deep_merge(value);
+                        continue;
+                    }
+                    _ => Value::new(value),
+                },
+                Err(value) => value,
+            };
+            self.merge_one(name, value);
+        }
+    }
+
+    /// Merge only specific parameters of other options.
+    ///
+    /// The parameters of `other` whose names are listed in `names` overwrite
+    /// those of `self` with the same names, and the rest of `other` is dropped.
+    /// The values are moved without cloning.
+    pub fn merge_subset(&mut self, mut other: Options<S>, names: &[&str]) {
+        for name in names {
+            if let Some((name, value)) = other.parameters.remove_entry(*name) {
+                self.merge_one(name, value);
+            }
+        }
+    }
+
+    /// Merge other options by cloning their values.
+    ///
+    /// The values of `other` are cloned (see `Value::try_clone`) and overwrite
+    /// those of `self` with the same names, and `other` is left intact. The
+    /// values that cannot be cloned are skipped.
+    pub fn merge_cloning(&mut self, other: &Options<S>) {
+        for (name, value) in &other.parameters {
+            if let Some(value) = value.try_clone() {
+                self.merge_one(name.clone(), value);
+            }
+        }
+    }
+
+    /// Merge other options appending vectors.
+    ///
+    /// When both values with the same name are of type `Vec<T>`, the incoming
+    /// vector is appended to the existing one. Otherwise, including vectors of
+    /// other element types, the parameters of `other` overwrite those of `self`
+    /// with the same names.
+    pub fn merge_appending<T: Any>(&mut self, other: Options<S>) {
+        for (name, value) in other.parameters {
+            let value = match value.downcast::<Vec<T>>() {
+                Ok(mut value) => match self.get_mut::<Vec<T>>(&name) {
+                    Some(existing) => {
+                        existing.append(&mut value);
+                        continue;
+                    }
+                    _ => Value::new(value),
+                },
+                Err(value) => value,
+            };
+            self.merge_one(name, value);
+        }
+    }
+
+    /// Make the parameters match those of other options.
+    ///
+    /// The missing parameters are added, the extra ones are removed, and those
+    /// with different values are overwritten, all by cloning the values of
+    /// `target` (see `Value::try_clone`). Values are compared if they are of a
+    /// registered type and are otherwise considered different. Nested options
+    /// are reconciled recursively and reported as updated if anything in them
+    /// changes. The parameters of `target` that cannot be cloned are skipped,
+    /// and so are the protected parameters of `self` (see `protect`).
+    pub fn reconcile(&mut self, target: &Options<S>) -> Reconciliation {
+        let mut reconciliation = Reconciliation::default();
+        let extra = self
+            .parameters
+            .keys()
+            .filter(|name| !self.protected.contains(*name))
+            .filter(|name| !target.parameters.contains_key(&***name))
+            .cloned()
+            .collect::<Vec<_>>();
+        for name in extra {
+            self.delete(&name);
+            reconciliation.removed.push(name);
+        }
+        for (name, value) in &target.parameters {
+            if self.protected.contains(name) {
+                continue;
+            }
+            let list = match self.parameters.get_mut(&**name) {
+                Some(existing) if existing.type_id == value.type_id => {
+                    if let Some(options) = value.get_ref::<Options>() {
+                        if let Some((_, journal)) = self.checkpoints.last_mut() {
+                            record_copy(journal, name, existing);
+                        }
+                        if let Some(nested) = existing.get_mut::<Options>() {
+                            if nested.reconcile(options) != Reconciliation::default() {
+                                self.version += 1;
+                                existing.modified = self.version;
+                                reconciliation.updated.push(name.clone());
+                            }
+                            continue;
+                        }
+                    }
+                    if types::eq(existing.as_any(), value.as_any()) == Some(true) {
+                        continue;
+                    }
+                    &mut reconciliation.updated
+                }
+                Some(_) => &mut reconciliation.updated,
+                _ => &mut reconciliation.added,
+            };
+            if let Some(value) = value.try_clone() {
+                list.push(name.clone());
+                self.insert(name.clone(), value);
+            }
+        }
+        reconciliation.added.sort();
+        reconciliation.removed.sort();
+        reconciliation.updated.sort();
+        reconciliation
+    }
+
+    /// Apply changes in a closure and revert them if it fails.
+    ///
+    /// While the closure runs, the values it overwrites or removes are kept
+    /// aside instead of being dropped, and nothing is cloned up front. If the
+    /// closure returns an error, the kept values are moved back into place,
+    /// and the parameters added by the closure are removed. The values handed
+    /// out, as by `remove_value` and `drain`, or modified in place, as by
+    /// `get_mut` and `iter_mut`, cannot be kept and are instead cloned (see
+    /// `Value::try_clone`) before the first change; the ones that cannot be
+    /// cloned, including nested options containing such values, are left as
+    /// the closure leaves them.
+    pub fn batch<F, E>(&mut self, f: F) -> Result<(), E>
+    where
+        F: FnOnce(&mut Options<S>) -> Result<(), E>,
+    {
+        let depth = self.checkpoints.len();
+        self.checkpoints.push((None, Journal::new()));
+        let result = f(self);
+        match result {
+            Ok(_) => {
+                let (_, journal) = self.checkpoints.remove(depth);
+                if let Some((_, below)) = depth.checked_sub(1).map(|i| &mut self.checkpoints[i]) {
+                    for (name, value) in journal {
+                        below.entry(name).or_insert(value);
+                    }
+                }
+            }
+            _ => {
+                while self.checkpoints.len() > depth {
+                    if let Some((_, journal)) = self.checkpoints.pop() {
+                        self.undo(journal);
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// Save the current state under a label for `rollback`.
+    ///
+    /// Nothing is cloned at this point. Instead, checkpoints are kept on a
+    /// stack, and the values overwritten or removed after the most recent one
+    /// are kept aside as in `batch`, which has the same limits for the values
+    /// handed out or modified in place. A label can be reused, in which case
+    /// `rollback` finds the most recent checkpoint with that label.
+    #[inline]
+    pub fn checkpoint(&mut self, label: &str) {
+        self.checkpoints
+            .push((Some(label.to_string()), Journal::new()));
+    }
+
+    /// Restore the state saved by `checkpoint` under a label.
+    ///
+    /// The parameters are restored as in `batch`. The checkpoint is removed from
+    /// the stack together with all the checkpoints pushed after it, so rolling
+    /// back to an earlier checkpoint discards the intermediate ones. Within the
+    /// closure given to `batch`, only the checkpoints saved in the closure are
+    /// considered. If there is no checkpoint with the label, nothing happens,
+    /// and `false` is returned.
+    pub fn rollback(&mut self, label: &str) -> bool {
+        let position = self
+            .checkpoints
+            .iter()
+            .rposition(|(one, _)| one.as_deref().is_none_or(|one| one == label));
+        let position = match position {
+            Some(position) if self.checkpoints[position].0.is_some() => position,
+            _ => return false,
+        };
+        while self.checkpoints.len() > position {
+            if let Some((_, journal)) = self.checkpoints.pop() {
+                self.undo(journal);
+            }
+        }
+        true
+    }
+
+    /// Merge other options according to a strategy.
+    ///
+    /// Only `MergeStrategy::ErrorOnConflict` can result in an error. Except for
+    /// `MergeStrategy::KeepExisting`, which ignores them, cleared parameters in
+    /// `other` remove those in `self` (see `set_clear`).
+    pub fn merge_using(
+        &mut self,
+        other: Options<S>,
+        strategy: MergeStrategy,
+    ) -> Result<(), Vec<Conflict>> {
+        match strategy {
+            MergeStrategy::Overwrite => {
+                for (name, value) in other.parameters {
+                    self.merge_one(name, value);
+                }
+            }
+            MergeStrategy::KeepExisting => {
+                for (name, value) in other.parameters {
+                    if value.type_id != TypeId::of::<Clear>() && !self.has(&name) {
+                        self.insert(name, value);
+                    }
+                }
+            }
+            MergeStrategy::ErrorOnConflict => return self.try_merge(other),
+            MergeStrategy::DeepMerge => self.deep_merge(other),
+        }
+        Ok(())
+    }
+
+    /// Merge other options recording where the values came from.
+    ///
+    /// The parameters of `other` overwrite those of `self` with the same names
+    /// and are labeled with `source`, which is then reported by `source_of`
+    /// until the parameters are set again by other means. The names of the
+    /// merged parameters are returned.
+    pub fn merge_tracked(&mut self, other: Options<S>, source: &str) -> Vec<Name> {
+        let source: Rc<str> = source.into();
+        let mut names = Vec::with_capacity(other.parameters.len());
+        for (name, mut value) in other.parameters {
+            value.metadata_mut().source = Some(source.clone());
+            names.push(name.clone());
+            self.merge_one(name, value);
+        }
+        names
+    }
+
+    /// Mark a parameter for removal when merging.
+    ///
+    /// The parameter is set to `Options::CLEAR`. When the options are merged
+    /// into others via `from_layers`, `try_merge`, `deep_merge`, `merge_subset`,
+    /// `merge_cloning`, `merge_appending`, `merge_using`, or `merge_tracked`,
+    /// the parameter with the same name is removed from the result instead of
+    /// being overwritten, which allows a layer to unset a parameter inherited
+    /// from an earlier one.
+    #[inline]
+    pub fn set_clear(&mut self, name: &str) -> &mut Options<S> {
+        self.set(name, Clear)
+    }
+
+    /// Set the value of a parameter validating the name.
+    ///
+    /// The name is checked against the policy given to `with_key_policy` and
+    /// against the protected names (see `protect`).
+    pub fn set_checked<U: Any>(
+        &mut self,
+        name: &str,
+        value: U,
+    ) -> Result<&mut Options<S>, KeyError> {
+        self.policy.check(name)?;
+        let name = self.canonical(Cow::Owned(name.to_string()));
+        if self.protected.contains(&name) {
+            return Err(KeyError::Protected);
+        }
+        self.overwrite(name, Value::new(value));
+        Ok(self)
+    }
+
+    /// Protect parameters from changes.
+    ///
+    /// Setting a protected parameter via `set` and the like and removing it via
+    /// `remove_value` and the like are silently ignored. The methods removing
+    /// or renaming several parameters at once, such as `clear`, `drain`,
+    /// `retain_typed`, and `rename_all`, leave it in place, and so do `batch`
+    /// and `rollback` when restoring. `set_checked` reports an error instead.
+    /// Changes made through mutable references, such as those given by
+    /// `get_mut`, are not prevented, and neither are the insertions made by
+    /// `get_or_insert_ref` and `path_entry_or_insert`.
+    pub fn protect(&mut self, names: &[&str]) {
+        for name in names {
+            let name = self.canonical(Cow::Owned(name.to_string()));
+            self.protected.insert(name);
+        }
+    }
+
+    /// Stop protecting parameters from changes.
+    ///
+    /// See `protect`.
+    pub fn unprotect(&mut self, names: &[&str]) {
+        for name in names {
+            let name = self.canonical(Cow::Owned(name.to_string()));
+            self.protected.remove(&name);
+        }
+    }
+
+    /// Set the value of a parameter to one shared with other parameters.
+    ///
+    /// See `Value::shared` for the semantics.
+    #[inline]
+    pub fn set_shared<T: Into<String>, U: Any + Clone>(
+        &mut self,
+        name: T,
+        value: Rc<U>,
+    ) -> &mut Options<S> {
+        self.insert(Cow::Owned(name.into()), Value::shared(value));
+        self
+    }
+
+    /// Set the value of a parameter that expires after a period of time.
+    ///
+    /// Expiry is checked on access rather than by a timer. Once expired, the
+    /// parameter is treated as absent by the methods looking parameters up by
+    /// name, such as `get`, `get_ref`, `has`, and `get_duration`, and by
+    /// `display_map` and the methods based on it, and it is removed by
+    /// `get_mut` and `purge_expired`. The other methods, such as `iter`, see
+    /// the parameter until it is removed.
+    pub fn set_with_ttl<U: Any>(&mut self, name: &str, value: U, ttl: Duration) -> &mut Options<S> {
+        let mut value = Value::new(value);
+        value.metadata_mut().expires = Instant::now().checked_add(ttl);
+        self.insert(Cow::Owned(name.into()), value);
+        self
+    }
+
+    /// Remove the expired parameters and return their number.
+    ///
+    /// See `set_with_ttl`.
+    pub fn purge_expired(&mut self) -> usize {
+        self.remove_where(|_, value| value.expired()).len()
+    }
+
+    /// Set the value of a parameter to be computed on first access.
+    ///
+    /// The initializer is called at most once, when the value is read via
+    /// `get`, `get_ref`, or `get_mut` for the first time. Inspecting or cloning
+    /// the value, as `batch` and `checkpoint` do, counts as reading it.
+    pub fn set_lazy<T, F>(&mut self, name: &str, initialize: F) -> &mut Options<S>
+    where
+        T: Any,
+        F: FnOnce() -> T + 'static,
+    {
+        let value = Lazy {
+            value: OnceCell::new(),
+            initialize: Cell::new(Some(Box::new(initialize) as Box<dyn FnOnce() -> T>)),
+        };
+        let value = Value {
+            inner: Box::new(value),
+            type_id: TypeId::of::<T>(),
+            type_name: std::any::type_name::<T>(),
+            access: Some(
+                const {
+                    &Access {
+                        get: Lazy::<T>::get,
+                        get_mut: Lazy::<T>::get_mut,
+                        into_inner: Lazy::<T>::into_inner,
+                        clone: None,
+                    }
+                },
+            ),
+            sequence: 0,
+            modified: 0,
+            metadata: None,
+        };
+        self.insert(Cow::Owned(name.into()), value);
+        self
+    }
+
+    /// Convert all values of one type into another type.
+    ///
+    /// The values are moved into the conversion without cloning, and the
+    /// number of converted values is returned.
+    pub fn map_type<T: Any, U: Any, F: FnMut(T) -> U>(&mut self, mut f: F) -> usize {
+        self.record_copies(|_, value| value.type_id == TypeId::of::<T>());
+        let mut count = 0;
+        for value in self.parameters.values_mut() {
+            if value.type_id == TypeId::of::<T>() {
+                match value.replace(()).downcast::<T>() {
+                    Ok(inner) => value.set(f(*inner)),
+                    _ => unreachable!(),
+                }
+                value.modified = self.version + 1;
+                count += 1;
+            }
+        }
+        if count > 0 {
+            self.version += 1;
+        }
+        count
+    }
+
+    /// Make equal values of a specific type share one allocation.
+    ///
+    /// All the values of type `T` become shared values (see `Value::shared`),
+    /// and equal values share the same `Rc`. The metadata is kept as in
+    /// `Value::replace`. The number of values that now share an allocation
+    /// with a value visited earlier is returned.
+    pub fn dedup_values<T: Any + Clone + PartialEq>(&mut self) -> usize {
+        self.record_copies(|_, value| value.type_id == TypeId::of::<T>());
+        let mut shared: Vec<Rc<T>> = Vec::new();
+        let mut count = 0;
+        for value in self.parameters.values_mut() {
+            if value.type_id != TypeId::of::<T>() {
+                continue;
+            }
+            let inner = match value.replace(()).downcast::<T>() {
+                Ok(inner) => *inner,
+                _ => unreachable!(),
+            };
+            let inner = match shared.iter().find(|other| ***other == inner) {
+                Some(other) => {
+                    count += 1;
+                    other.clone()
+                }
+                _ => {
+                    let inner = Rc::new(inner);
+                    shared.push(inner.clone());
+                    inner
+                }
+            };
+            value.swap(Value::shared(inner));
+            value.modified = self.version + 1;
+        }
+        if !shared.is_empty() {
+            self.version += 1;
+        }
+        count
+    }
+
+    /// Rename all parameters via a mapping of names.
+    ///
+    /// The new names are passed through the key normalizer if one is set. If
+    /// several names are mapped to the same one, the parameter visited last
+    /// wins. Since the order of visiting is unspecified, so is the winner. The
+    /// protected parameters (see `protect`) keep their names, and those mapped
+    /// onto protected names are dropped as with `set`.
+    pub fn rename_all<F: FnMut(&str) -> String>(&mut self, mut f: F) {
+        let protected = &self.protected;
+        let parameters = self
+            .parameters
+            .extract_if(|name, _| !protected.contains(name))
+            .collect::<Vec<_>>();
+        if parameters.is_empty() {
+            return;
+        }
+        self.version += 1;
+        if let Some((_, journal)) = self.checkpoints.last_mut() {
+            for (name, value) in &parameters {
+                record_copy(journal, name, value);
+            }
+        }
+        for (name, mut value) in parameters {
+            let name = Cow::Owned(self.normalize(&f(&name)).into_owned());
+            if !self.protected.contains(&name) {
+                self.record(&name, None);
+                value.modified = self.version;
+                self.parameters.insert(name, value);
+            }
+        }
+    }
+
+    /// Retain the parameters of a specific type satisfying a predicate.
+    ///
+    /// The parameters of other types are retained without being visited.
+    pub fn retain_typed<T: Any, F: FnMut(&str, &mut T) -> bool>(&mut self, mut f: F) {
+        let version = self.version + 1;
+        let mut visited = false;
+        self.record_copies(|_, value| value.type_id == TypeId::of::<T>());
+        self.remove_where(|name, value| {
+            let retain = match value.get_mut::<T>() {
+                Some(inner) => f(name, inner),
+                _ => return false,
+            };
+            value.modified = version;
+            visited = true;
+            !retain
+        });
+        if visited {
+            self.version = version;
+        }
+    }
+
+    /// Retain the values that are not options whose paths satisfy a predicate.
+    ///
+    /// The values of nested options are visited recursively with the paths
+    /// formed as in `walk_leaves`. The nested options left empty, including
+    /// those that were empty to begin with, are removed.
+    pub fn retain_paths<F: FnMut(&str) -> bool>(&mut self, mut f: F) {
+        self.retain_paths_in("", &mut f);
+    }
+
+    /// Retain the parameters satisfying a predicate and return the names of
+    /// the removed ones.
+    pub fn retain_reporting<F: FnMut(&str, &Value) -> bool>(&mut self, mut f: F) -> Vec<Name> {
+        self.remove_where(|name, value| !f(name, value))
+    }
+
+    /// Retain only the parameters inserted at or after an instant.
+    ///
+    /// The time of insertion is recorded only by the options created via
+    /// `with_timestamps`, whenever a parameter is set, and modifying the value
+    /// in place does not change it. The parameters whose time of insertion is
+    /// unknown, such as those given to `from_str_pairs`, are retained. The
+    /// number of removed parameters is returned.
+    pub fn retain_newer_than(&mut self, cutoff: Instant) -> usize {
+        self.remove_where(|_, value| value.inserted().is_some_and(|inserted| inserted < cutoff))
+            .len()
+    }
+
+    /// Retain only the parameters whose names are present in `other`.
+    ///
+    /// The number of removed parameters is returned.
+    pub fn retain_keys_of(&mut self, other: &Options<S>) -> usize {
+        self.remove_where(|name, _| !other.has(name)).len()
+    }
+
+    /// Remove the parameters whose values are empty and return their number.
+    ///
+    /// A value is considered empty if it is an empty `String`, `&'static str`,
+    /// or vector of a registered type (see `bytes_estimate`) or if it is nested
+    /// options with no parameters. Nested options are not pruned recursively.
+    pub fn prune_empty(&mut self) -> usize {
+        self.remove_where(|_, value| match value.get_ref::<Options>() {
+            Some(options) => options.parameters.is_empty(),
+            _ => types::is_empty(value.as_any()).unwrap_or(false),
+        })
+        .len()
+    }
+
+    /// Remove the parameters of a specific type whose values are equal to the
+    /// default and return their number.
+    pub fn prune_defaults<T: Any + Default + PartialEq>(&mut self) -> usize {
+        let default = T::default();
+        self.remove_where(|_, value| value.get_ref::<T>() == Some(&default))
+            .len()
+    }
+
+    /// Move the values of aliases onto their canonical names.
+    ///
+    /// Each entry of `aliases` is a canonical name followed by its aliases. If
+    /// the canonical name is present, its value is kept. Otherwise, the value of
+    /// the first present alias in the order given is moved onto the canonical
+    /// name. In either case, all the aliases are removed.
+    pub fn canonicalize(&mut self, aliases: &[(&str, &[&str])]) {
+        for (canonical, aliases) in aliases {
+            for alias in aliases.iter() {
+                if let Some((_, value)) = self.remove(alias) {
+                    if !self.has(canonical) {
+                        self.insert(Cow::Owned(canonical.to_string()), value);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Replace owned names with static ones where they coincide.
+    ///
+    /// For long-lived options whose names come from a set known in advance, the
+    /// method releases the memory allocated for owned names. The names that are
+    /// not present in `known` are left as they are.
+    pub fn intern_static(&mut self, known: &[&'static str]) {
+        for name in known {
+            if let Some((Cow::Owned(_), value)) = self.parameters.remove_entry(*name) {
+                self.parameters.insert(Cow::Borrowed(*name), value);
+            }
+        }
+    }
+
+    /// Check if two parameters refer to the same allocation.
+    ///
+    /// Since each value is boxed separately, this can be the case only for
+    /// values set via `set_shared` from clones of the same `Rc`. Values of
+    /// zero-sized types are never considered the same.
+    pub fn same_value(&self, a: &str, b: &str) -> bool {
+        match (self.live(a), self.live(b)) {
+            (Some(a), Some(b)) => {
+                let (a, b) = (a.as_any(), b.as_any());
+                std::mem::size_of_val(a) > 0
+                    && std::ptr::eq(
+                        a as *const dyn Any as *const (),
+                        b as *const dyn Any as *const (),
+                    )
+            }
+            _ => false,
+        }
+    }
+
+    /// Return the time elapsed since a parameter was inserted.
+    ///
+    /// See `retain_newer_than` for when the time of insertion is recorded.
+    pub fn age_of(&self, name: &str) -> Option<Duration> {
+        self.live(name)?
+            .inserted()
+            .map(|inserted| inserted.elapsed())
+    }
+
+    /// Check that parameters are present with values of specific types.
+    ///
+    /// The check is performed only when debug assertions are enabled, in which
+    /// case the first missing or mistyped parameter in `schema` causes a panic.
+    /// Otherwise, the method does nothing.
+    pub fn debug_assert_schema(&self, schema: &[(&str, TypeId)]) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+        for (name, type_id) in schema {
+            match self.live(name) {
+                Some(value) if value.type_id == *type_id => {}
+                Some(value) => panic!(
+                    "the parameter {name:?} is of type {}, which is not the expected one",
+                    value.type_name,
+                ),
+                _ => panic!("the parameter {name:?} is missing"),
+            }
+        }
+    }
+
+    /// Validate parameters with custom checks.
+    ///
+    /// Each validator is run against the value of the parameter it is named
+    /// after, and all failures are collected as pairs of a name and a message.
+    /// A missing parameter counts as a failure.
+    pub fn validate_each(
+        &self,
+        validators: &[(&str, Validator)],
+    ) -> Result<(), Vec<(String, String)>> {
+        let failures = validators
+            .iter()
+            .filter_map(|(name, validate)| {
+                let result = match self.live(name) {
+                    Some(value) => validate(value),
+                    _ => Err(GetError::Missing.to_string()),
+                };
+                result.err().map(|message| (name.to_string(), message))
+            })
+            .collect::<Vec<_>>();
+        match failures.is_empty() {
+            true => Ok(()),
+            _ => Err(failures),
+        }
+    }
+
+    /// Count the parameters satisfying a predicate.
+    #[inline]
+    pub fn count<F: FnMut(&str, &Value) -> bool>(&self, mut predicate: F) -> usize {
+        self.parameters
+            .iter()
+            .filter(|(name, value)| predicate(name, value))
+            .count()
+    }
+
+    /// Return the label of the source of a parameter.
+    ///
+    /// See `merge_tracked`.
+    pub fn source_of(&self, name: &str) -> Option<&str> {
+        self.live(name)?.metadata.as_ref()?.source.as_deref()
+    }
+
+    /// Check the presence of a parameter.
+    #[inline]
+    pub fn has(&self, name: &str) -> bool {
+        self.live(name).is_some()
+    }
+
+    /// Return the version, which is incremented on every modification.
+    ///
+    /// The version allows for detecting changes cheaply by comparing it with a
+    /// previously observed one. It counts `set`, `remove_value`, `clear`, and
+    /// the other modifying calls, including the ones handing out mutable
+    /// references such as `get_mut` and `iter_mut`, since the changes made via
+    /// those references cannot be observed. The calls that find nothing to
+    /// modify leave the version as is. The version is specific to each
+    /// instance and cannot be reset.
+    #[inline]
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Return the names of the parameters changed after a version.
+    ///
+    /// A parameter is considered changed when it is set or a mutable reference
+    /// to its value is handed out, as with `get_mut` and `iter_mut`; see
+    /// `version`. The names are returned in ascending order.
+    pub fn changed_since(&self, version: u64) -> Vec<&Name> {
+        let mut names = self
+            .parameters
+            .iter()
+            .filter(|(_, value)| value.modified > version)
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    /// Return the version of nested options.
+    ///
+    /// The nested options are reached via mutable references handed out by
+    /// their parents, such as those given by `get_path_mut`, so a change in
+    /// nested options advances the versions of all the options on the path
+    /// leading to them but not those of the other nested options. An empty path
+    /// refers to `self`.
+    pub fn path_version(&self, path: &str) -> Option<u64> {
+        match path.is_empty() {
+            true => Some(self.version),
+            _ => self.get_path_ref::<Options>(path).map(Options::version),
+        }
+    }
+
+    /// Remove the parameters satisfying a predicate and return them.
+    ///
+    /// The values are moved without cloning.
+    pub fn extract<F>(&mut self, mut predicate: F) -> Options<S>
+    where
+        F: FnMut(&str, &Value) -> bool,
+        S: Clone,
+    {
+        let mut other = self.empty();
+        let protected = &self.protected;
+        other.parameters.extend(
+            self.parameters
+                .extract_if(|name, value| !protected.contains(name) && predicate(name, value)),
+        );
+        if !other.parameters.is_empty() {
+            self.version += 1;
+        }
+        if let Some((_, journal)) = self.checkpoints.last_mut() {
+            for (name, value) in &other.parameters {
+                record_copy(journal, name, value);
+            }
+        }
+        other
+    }
+
+    /// Split into the parameters satisfying a predicate and the rest.
+    ///
+    /// The values are moved without cloning.
+    pub fn partition<F>(self, mut predicate: F) -> (Options<S>, Options<S>)
+    where
+        F: FnMut(&str, &Value) -> bool,
+        S: Clone,
+    {
+        let mut one = self.empty();
+        let mut other = self.empty();
+        for (name, value) in self.parameters {
+            if predicate(&name, &value) {
+                one.parameters.insert(name, value);
+            } else {
+                other.parameters.insert(name, value);
+            }
+        }
+        (one, other)
+    }
+
+    /// Convert into immutable options that can be shared between threads.
+    ///
+    /// Only values that are `Send` and `Sync` can be shared, and, since values
+    /// are type-erased, they are recognized among the registered types (see
+    /// `bytes_estimate`) and `Json`. Nested options are converted in the same
+    /// manner. The other values are skipped with a warning emitted via the hook
+    /// given to `set_warning_hook`.
+    #[inline]
+    pub fn into_shared(self) -> Arc<FrozenOptions> {
+        Arc::new(self.freeze())
+    }
+
+    /// Split into the parameters of a specific type and the rest.
+    ///
+    /// The values are moved without cloning.
+    pub fn into_typed_map<T: Any>(self) -> (HashMap<Name, T>, Options<S>)
+    where
+        S: Clone,
+    {
+        let mut typed = HashMap::new();
+        let mut other = self.empty();
+        for (name, value) in self.parameters {
+            match value.downcast::<T>() {
+                Ok(value) => {
+                    typed.insert(name, value);
+                }
+                Err(value) => {
+                    other.parameters.insert(name, value);
+                }
+            }
+        }
+        (typed, other)
+    }
+
+    /// Collapse nested options into parameters named by paths.
+    ///
+    /// Each value that is not options is cloned (see `Value::try_clone`) into a
+    /// parameter named by the path leading to it as in `walk_leaves`, such as
+    /// `"db.host"`. The values that cannot be cloned and empty nested options
+    /// are skipped.
+    pub fn flatten(&self) -> Options<S>
+    where
+        S: Clone,
+    {
+        let mut other = self.empty();
+        for (path, value) in self.walk_leaves() {
+            if let Some(value) = value.try_clone() {
+                other.parameters.insert(Cow::Owned(path), value);
+            }
+        }
+        other
+    }
+
+    /// Return all the values that are not options together with their paths.
+    ///
+    /// The values of nested options are visited recursively, and the names
+    /// leading to each value are joined by dots, such as `"db.host"`, with the
+    /// dots and backslashes within names escaped (see `path`). Nested options
+    /// themselves are not included. The result is sorted by path.
+    pub fn walk_leaves(&self) -> Vec<(String, &Value)> {
+        let mut leaves = Vec::new();
+        self.collect_leaves("", &mut leaves);
+        leaves.sort_unstable_by(|one, other| one.0.cmp(&other.0));
+        leaves
+    }
+
+    /// Return all the values of a specific type together with their paths.
+    ///
+    /// The values are visited in the same manner as by `walk_leaves`, and those
+    /// of other types are skipped. The result is sorted by path.
+    pub fn walk_typed<T: Any + Clone>(&self) -> Vec<(String, T)> {
+        self.walk_leaves()
+            .into_iter()
+            .filter_map(|(path, value)| Some((path, value.get()?)))
+            .collect()
+    }
+
+    /// Count the values that are not options.
+    ///
+    /// The values are counted in the same manner as they are visited by
+    /// `walk_leaves` but without collecting their paths.
+    pub fn leaf_count(&self) -> usize {
+        self.parameters
+            .values()
+            .map(|value| match value.get_ref::<Options>() {
+                Some(options) => options.leaf_count(),
+                _ => 1,
+            })
+            .sum()
+    }
+
+    /// Expand parameters named by paths into nested options.
+    ///
+    /// Each name is split by `separator` taking escaping into account (see
+    /// `path::parse_path_escaped`), and the value is cloned (see
+    /// `Value::try_clone`) into the nested options given by all the segments but
+    /// the last one; the values that cannot be cloned are skipped. If a name is
+    /// both a name of a value and a prefix of other names, such as `"db"` along
+    /// with `"db.host"`, the nested options take precedence, and the value is
+    /// skipped.
+    pub fn unflatten(&self, separator: char) -> Options<S>
+    where
+        S: Clone,
+    {
+        fn place<S: BuildHasher>(
+            options: &mut Options<S>,
+            path: &str,
+            separator: char,
+            value: Value,
+        ) {
+            let (name, rest) = match path::split_first(path, separator) {
+                (name, Some(rest)) => (name, rest),
+                (name, _) => {
+                    if options.get_ref::<Options>(&name).is_none() {
+                        options.parameters.insert(Cow::Owned(name.into()), value);
+                    }
+                    return;
+                }
+            };
+            if options.get_ref::<Options>(&name).is_none() {
+                options
+                    .parameters
+                    .insert(Cow::Owned(name.to_string()), Value::new(Options::new()));
+            }
+            let options = options.parameters.get_mut(&*name).unwrap();
+            place(
+                options.get_mut::<Options>().unwrap(),
+                rest,
+                separator,
+                value,
+            );
+        }
+        let mut other = self.empty();
+        for (name, value) in &self.parameters {
+            if let Some(value) = value.try_clone() {
+                place(&mut other, name, separator, value);
+            }
+        }
+        other
+    }
+
+    /// Return a view of the parameters whose names start with a prefix.
+    #[inline]
+    pub fn namespace<'l>(&'l self, prefix: &'l str) -> Namespace<'l, S> {
+        Namespace {
+            options: self,
+            prefix,
+        }
+    }
+
+    /// Return a mutable view of the parameters whose names start with a prefix.
+    #[inline]
+    pub fn namespace_mut<'l>(&'l mut self, prefix: &'l str) -> NamespaceMut<'l, S> {
+        NamespaceMut {
+            options: self,
+            prefix,
+        }
+    }
+
+    /// Return a view where `top` takes precedence over `self`.
+    #[inline]
+    pub fn overlay<'l>(&'l self, top: &'l Options<S>) -> Overlay<'l, S> {
+        Overlay { base: self, top }
+    }
+
+    /// Return a guard that reverts the changes made through it when dropped.
+    #[inline]
+    pub fn scoped(&mut self) -> ScopedOptions<'_, S> {
+        self.checkpoints.push((None, Journal::new()));
+        ScopedOptions { options: self }
+    }
+
+    /// Estimate the amount of memory occupied by the parameters.
+    ///
+    /// The estimate includes the storage allocated by the underlying map, the
+    /// names, and the values. Values of registered types (see below) and nested
+    /// options are accounted for together with the memory they own, such as the
+    /// buffers of `String` and `Vec`. Values of other types are counted as the
+    /// size of a pointer, as their contents cannot be inspected. The registered
+    /// types are the primitive scalars, `String`, `&'static str`, and vectors
+    /// of these.
+    pub fn bytes_estimate(&self) -> usize {
+        let mut total = self.parameters.capacity() * (size_of::<Name>() + size_of::<Value>() + 1);
+        for (name, value) in self.parameters.iter() {
+            if let Cow::Owned(name) = name {
+                total += name.capacity();
+            }
+            total += match value.get_ref::<Options>() {
+                Some(options) => size_of::<Options>() + options.bytes_estimate(),
+                _ => types::size(value.as_any()).unwrap_or(size_of::<Box<dyn Any>>()),
+            };
+        }
+        total
+    }
+
+    /// Shrink the capacity of the storage while keeping some headroom.
+    ///
+    /// The capacity remains at least as large as both the number of parameters
+    /// and `min_capacity`.
+    #[inline]
+    pub fn shrink_to(&mut self, min_capacity: usize) {
+        self.parameters.shrink_to(min_capacity);
+    }
+
+    /// Return statistics about the storage.
+    pub fn stats(&self) -> OptionsStats {
+        let len = self.parameters.len();
+        let capacity = self.parameters.capacity();
+        let mut types = self
+            .parameters
+            .values()
+            .map(|value| value.type_id)
+            .collect::<Vec<_>>();
+        types.sort_unstable();
+        types.dedup();
+        OptionsStats {
+            len,
+            capacity,
+            load_factor: if capacity == 0 {
+                0.0
+            } else {
+                len as f64 / capacity as f64
+            },
+            distinct_types: types.len(),
+        }
+    }
+
+    /// Group the names by the types of their values.
+    ///
+    /// The groups are keyed by type names, and the names in each group are in
+    /// ascending order.
+    pub fn group_by_type(&self) -> HashMap<&'static str, Vec<&Name>> {
+        let mut groups: HashMap<&'static str, Vec<&Name>> = HashMap::new();
+        for (name, value) in &self.parameters {
+            groups.entry(value.type_name).or_default().push(name);
+        }
+        for names in groups.values_mut() {
+            names.sort();
+        }
+        groups
+    }
+
+    /// Mark parameters as secret.
+    ///
+    /// The values of secret parameters are rendered as `***` by `display_map`,
+    /// `to_sorted_pairs`, `write_properties`, `to_env_vars`, and `to_json`. The
+    /// names are matched against paths (see `get_path`), so nested parameters
+    /// can be marked too, and their first segments are normalized and resolved
+    /// as in `set`. The values remain accessible via `get` and the like.
+    pub fn set_secret(&mut self, names: &[&str]) {
+        for name in names {
+            let (name, rest) = path::split_first(name, '.');
+            let name = self.canonical(Cow::Owned(name.into_owned()));
+            let name = path::escape_segment(&name, '.');
+            let path = match rest {
+                Some(rest) => format!("{name}.{rest}"),
+                _ => name.into_owned(),
+            };
+            self.secrets.insert(Cow::Owned(path));
+        }
+    }
+
+    /// Render the values as strings ordered by name.
+    ///
+    /// Only the values of registered types (see `bytes_estimate`) are rendered;
+    /// the other parameters are skipped. Scalars and strings are rendered via
+    /// `Display` and vectors via `Debug`. Secret values are masked (see
+    /// `set_secret`).
+    pub fn display_map(&self) -> BTreeMap<&str, String> {
+        self.parameters
+            .iter()
+            .filter(|(_, value)| !value.expired())
+            .filter_map(|(name, value)| {
+                let value = self.render(&path::escape_segment(name, '.'), value)?;
+                Some((name.as_ref(), value))
+            })
+            .collect()
+    }
+
+    /// Render the values as pairs of strings ordered by name.
+    ///
+    /// The pairs are the entries of `display_map` with owned names.
+    pub fn to_sorted_pairs(&self) -> Vec<(String, String)> {
+        self.display_map()
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect()
+    }
+
+    /// Write the values as lines of the form `name=value` ordered by name.
+    ///
+    /// The values are rendered as in `display_map`, and the other parameters,
+    /// including nested options, are skipped; nested options can be written
+    /// after `flatten`. In the names and values, the following characters are
+    /// escaped with a backslash: a backslash, an equals sign, a line feed
+    /// (`\n`), a carriage return (`\r`), a tab (`\t`), spaces at the start or
+    /// the end, and `#` at the start of a name.
+    pub fn write_properties<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        for (name, value) in self.display_map() {
+            properties::write(writer, name, &value)?;
+        }
+        Ok(())
+    }
+
+    /// Render the values as environment variables.
+    ///
+    /// The name of a variable is `prefix` followed by the name of the parameter
+    /// with ASCII letters converted to uppercase and both dots and hyphens
+    /// replaced with underscores; for instance, with `"APP_"`, `"db.max-size"`
+    /// becomes `"APP_DB_MAX_SIZE"`. Nested options are rendered recursively
+    /// with their names joined by dots before the conversion. The values are
+    /// rendered as in `display_map`, and the other parameters are skipped.
+    pub fn to_env_vars(&self, prefix: &str) -> HashMap<String, String> {
+        self.walk_leaves()
+            .into_iter()
+            .filter_map(|(path, value)| {
+                let value = self.render(&path, value)?;
+                let name = path
+                    .replace('\\', "")
+                    .to_ascii_uppercase()
+                    .replace(['.', '-'], "_");
+                Some((format!("{prefix}{name}"), value))
+            })
+            .collect()
+    }
+
+    /// Convert to a JSON object.
+    ///
+    /// Nested options are converted to nested objects, and the values of the
+    /// other registered types (see `bytes_estimate`) and of type `Json` are
+    /// converted to the corresponding JSON values. The values of unregistered
+    /// types are converted to `null` so that the names are still listed. Secret
+    /// values are converted to `"***"` (see `set_secret`).
+    #[cfg(feature = "json")]
+    #[inline]
+    pub fn to_json(&self) -> Json {
+        self.json("", &self.secrets)
+    }
+
+    /// Convert to a TOML document.
+    ///
+    /// The parameters are converted as in `to_json`, with nested options
+    /// written as tables and vectors of JSON objects as arrays of tables. A
+    /// value that TOML cannot represent, which is a value of an unregistered
+    /// type or a JSON null, results in an error naming its path.
+    #[cfg(feature = "toml")]
+    #[inline]
+    pub fn to_toml_string(&self) -> Result<String, TomlError> {
+        toml::write(&self.to_json())
+    }
+
+    /// Return the groups of names that differ only in ASCII case.
+    ///
+    /// Each group has at least two names sorted in ascending order, and the
+    /// groups are sorted by their first names.
+    pub fn ci_collisions(&self) -> Vec<Vec<Name>> {
+        let mut groups: BTreeMap<String, Vec<Name>> = BTreeMap::new();
+        for name in self.names() {
+            groups
+                .entry(name.to_ascii_lowercase())
+                .or_default()
+                .push(name.clone());
+        }
+        let mut groups = groups
+            .into_values()
+            .filter(|group| group.len() > 1)
+            .map(|mut group| {
+                group.sort_unstable();
+                group
+            })
+            .collect::<Vec<_>>();
+        groups.sort_unstable();
+        groups
+    }
+
+    /// Return an iterator over the names present in `self` but not in `other`.
+    pub fn key_difference<'l>(&'l self, other: &'l Options<S>) -> impl Iterator<Item = &'l Name> {
+        self.names().filter(move |name| !other.has(name))
+    }
+
+    /// Return an iterator over the names present in both `self` and `other`.
+    pub fn key_intersection<'l>(&'l self, other: &'l Options<S>) -> impl Iterator<Item = &'l Name> {
+        self.names().filter(move |name| other.has(name))
+    }
+
+    /// Return an iterator over parameters.
+    pub fn iter(&self) -> Parameters<'_> {
+        Parameters {
+            iterator: self.parameters.iter(),
+        }
+    }
+
+    /// Return an iterator over parameters satisfying a predicate.
+    pub fn filter<'l, F>(&'l self, predicate: F) -> impl Iterator<Item = (&'l Name, &'l Value)>
+    where
+        F: Fn(&str, &Value) -> bool + 'l,
+    {
+        self.iter()
+            .filter(move |(name, value)| predicate(name, value))
+    }
+
+    /// Remove all parameters and return an iterator over them.
+    ///
+    /// The protected parameters (see `protect`) are left in place. The other
+    /// ones are removed even if the iterator is dropped before being exhausted.
+    #[inline]
+    pub fn drain(&mut self) -> Drain<'_> {
+        self.record_copies(|_, _| true);
+        let protected = &self.protected;
+        if self.parameters.keys().any(|name| !protected.contains(name)) {
+            self.version += 1;
+        }
+        Drain {
+            iterator: self
+                .parameters
+                .extract_if(Box::new(|name, _| !protected.contains(name))),
+        }
+    }
+
+    /// Call a function for each mutable parameter.
+    ///
+    /// Each parameter is considered changed (see `changed_since`), and the
+    /// version is incremented unless there are no parameters.
+    #[inline]
+    pub fn for_each_mut<F: FnMut(&str, &mut Value)>(&mut self, mut f: F) {
+        if self.parameters.is_empty() {
+            return;
+        }
+        self.record_copies(|_, _| true);
+        self.version += 1;
+        for (name, value) in self.parameters.iter_mut() {
+            value.modified = self.version;
+            f(name, value);
+        }
+    }
+
+    /// Get the parameter at a position in the order of insertion.
+    ///
+    /// A parameter moves to the end whenever it is set, and modifying the
+    /// value in place does not move it. The order is not stored separately but
+    /// derived from a counter stamped on each value, so each call takes time
+    /// linear in the number of parameters; for visiting all of them in order,
+    /// sort `iter` once instead. The values moved in from other options without
+    /// being set, as by `unflatten`, keep the stamps given there, so the order
+    /// is only partial: they are interleaved with the others arbitrarily.
+    pub fn get_at(&self, index: usize) -> Option<(&Name, &Value)> {
+        if index >= self.parameters.len() {
+            return None;
+        }
+        let mut parameters = self.parameters.iter().collect::<Vec<_>>();
+        let (_, parameter, _) =
+            parameters.select_nth_unstable_by_key(index, |(_, value)| value.sequence);
+        Some(*parameter)
+    }
+
+    /// Return an iterator over parameters with categorized values.
+    ///
+    /// The values that fall into none of the categories of `TypedRef` are given
+    /// as `TypedRef::Other`.
+    pub fn iter_typed(&self) -> impl Iterator<Item = (&Name, TypedRef<'_>)> {
+        self.parameters.iter().map(|(name, value)| {
+            let value = types::typed(value.as_any()).unwrap_or(TypedRef::Other(value.type_name));
+            (name, value)
+        })
+    }
+
+    /// Return an iterator over mutable parameters.
+    ///
+    /// Each parameter yielded by the iterator is considered changed (see
+    /// `changed_since`), and the version is incremented unless there are no
+    /// parameters.
+    pub fn iter_mut(&mut self) -> ParametersMut<'_> {
+        self.record_copies(|_, _| true);
+        if !self.parameters.is_empty() {
+            self.version += 1;
+        }
+        ParametersMut {
+            iterator: self.parameters.iter_mut(),
+            version: self.version,
+        }
+    }
+
+    /// Return an iterator over names.
+    #[inline]
+    pub fn names(&self) -> Names<'_> {
+        fn first<'l>((name, _): (&'l Name, &'l Value)) -> &'l Name {
+            name
+        }
+        Names {
+            iterator: self.parameters.iter().map(first),
+        }
+    }
+
+    /// Return an arbitrary name.
+    ///
+    /// The name is the first one yielded by `names`, and the order is
+    /// unspecified. If there are no parameters, `None` is returned.
+    #[inline]
+    pub fn any_name(&self) -> Option<&Name> {
+        self.names().next()
+    }
+
+    /// Return an iterator over the names matching a pattern.
+    ///
+    /// In the pattern, `*` matches any sequence of characters, including an
+    /// empty one, and `?` matches any single character; the other characters
+    /// match themselves. For instance, `"*.timeout"` matches `"db.timeout"`.
+    pub fn names_matching<'l>(&'l self, pattern: &'l str) -> impl Iterator<Item = &'l Name> {
+        self.names().filter(move |name| glob(pattern, name))
+    }
+
+    /// Return the values that are not options with paths matching a pattern.
+    ///
+    /// The values are visited in the same manner as by `walk_leaves`. The
+    /// pattern and the paths are split into segments by dots taking escaping
+    /// into account (see `path::parse_path_escaped`), and `**` as a
+    /// whole segment matches any number of segments, including none; the other
+    /// segments are matched as in `names_matching`. For instance, `"**.timeout"`
+    /// matches `"timeout"` and `"db.primary.timeout"`. The result is sorted by
+    /// path.
+    pub fn glob(&self, pattern: &str) -> Vec<(String, &Value)> {
+        let pattern = path::parse_path_escaped(pattern, '.');
+        let mut leaves = self.walk_leaves();
+        leaves.retain(|(path, _)| glob_path(&pattern, &path::parse_path_escaped(path, '.')));
+        leaves
+    }
+
+    /// Return the names in ascending order.
+    ///
+    /// The sorted names are cached and reused until the options are modified
+    /// (see `version`).
+    pub fn sorted_names_cached(&mut self) -> &[Name] {
+        let version = self.version;
+        if !matches!(self.sorted_names, Some((cached, _)) if cached == version) {
+            let mut names = self.parameters.keys().cloned().collect::<Vec<_>>();
+            names.sort_unstable();
+            self.sorted_names = Some((version, names));
+        }
+        match &self.sorted_names {
+            Some((_, names)) => names,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Return the names sorted via a comparator.
+    pub fn names_sorted_by<F>(&self, mut compare: F) -> Vec<&Name>
+    where
+        F: FnMut(&Name, &Name) -> Ordering,
+    {
+        let mut names = self.names().collect::<Vec<_>>();
+        names.sort_by(|one, other| compare(one, other));
+        names
+    }
+
+    fn canonical(&self, mut name: Name) -> Name {
+        if let Some(normalizer) = self.normalizer {
+            name = Cow::Owned(normalizer(&name));
+        }
+        if let Some(canonical) = self.aliases.get(&*name) {
+            name = canonical.clone();
+        }
+        name
+    }
+
+    fn clone_cloneable(&self) -> Options<S>
+    where
+        S: Clone,
+    {
+        let mut other = self.empty();
+        other.parameters.extend(
+            self.parameters
+                .iter()
+                .filter_map(|(name, value)| Some((name.clone(), value.try_clone()?))),
+        );
+        other
+    }
+
+    fn collect_leaves<'l>(&'l self, prefix: &str, leaves: &mut Vec<(String, &'l Value)>) {
+        for (name, value) in &self.parameters {
+            let path = format!("{prefix}{}", path::escape_segment(name, '.'));
+            match value.get_ref::<Options>() {
+                Some(options) => options.collect_leaves(&format!("{path}."), leaves),
+                _ => leaves.push((path, value)),
+            }
+        }
+    }
+
+    fn collect_path<T>(&self, prefix: &str, segments: &[&str], values: &mut Vec<(String, T)>)
+    where
+        T: Any + Clone,
+    {
+        let (segment, segments) = match segments.split_first() {
+            Some(pair) => pair,
+            _ => return,
+        };
+        let parameters: Box<dyn Iterator<Item = (&Name, &Value)>> = match *segment {
+            "*" => Box::new(self.parameters.iter()),
+            _ => match self.parameters.get_key_value(&*self.normalize(segment)) {
+                Some(parameter) => Box::new(std::iter::once(parameter)),
+                _ => return,
+            },
+        };
+        for (name, value) in parameters {
+            let path = format!("{prefix}{}", path::escape_segment(name, '.'));
+            if segments.is_empty() {
+                if let Some(value) = value.get() {
+                    values.push((path, value));
+                }
+            } else if let Some(options) = value.get_ref::<Options>() {
+                options.collect_path(&format!("{path}."), segments, values);
+            }
+        }
+    }
+
+    fn cow<T: Any + Clone + FromStr>(&self, name: &str) -> Option<Cow<'_, T>> {
+        match self.get_ref(name) {
+            Some(value) => Some(Cow::Borrowed(value)),
+            _ => self.get_str(name)?.parse().ok().map(Cow::Owned),
+        }
+    }
+
+    fn empty(&self) -> Options<S>
+    where
+        S: Clone,
+    {
+        Options {
+            parameters: HashMap::with_hasher(self.parameters.hasher().clone()),
+            aliases: self.aliases.clone(),
+            protected: self.protected.clone(),
+            secrets: self.secrets.clone(),
+            policy: self.policy,
+            normalizer: self.normalizer,
+            traits: self.traits.clone(),
+            factories: self.factories.clone(),
+            checkpoints: Vec::new(),
+            version: 0,
+            sorted_names: None,
+            timestamps: self.timestamps,
+            warning_hook: self.warning_hook.clone(),
+        }
+    }
+
+    fn freeze(self) -> FrozenOptions {
+        let mut parameters = HashMap::new();
+        let warning_hook = self.warning_hook;
+        for (name, value) in self.parameters {
+            if value.expired() {
+                continue;
+            }
+            let type_name = value.type_name;
+            let value = match value.downcast::<Options>() {
+                Ok(mut options) => {
+                    if options.warning_hook.is_none() {
+                        options.warning_hook = warning_hook.clone();
+                    }
+                    Ok(Box::new(options.freeze()) as Box<dyn Any + Send + Sync>)
+                }
+                Err(value) => types::into_sync(value.into_boxed()),
+            };
+            match value {
+                Ok(value) => {
+                    parameters.insert(name, value);
+                }
+                _ => warn(
+                    warning_hook.as_ref(),
+                    &format!(
+                        "the parameter {name:?} is of type {type_name}, which cannot be shared"
+                    ),
+                ),
+            }
+        }
+        FrozenOptions { parameters }
+    }
+
+    fn get_or_parse<T: Any + Clone + FromStr>(&self, name: &str) -> Option<T> {
+        match self.get(name) {
+            Some(value) => Some(value),
+            _ => self.get_str(name)?.parse().ok(),
+        }
+    }
+
+    fn get_str(&self, name: &str) -> Option<&str> {
+        let value = self.live(name)?;
+        match value.get_ref::<String>() {
+            Some(value) => Some(value),
+            _ => value.get_ref::<&'static str>().copied(),
+        }
+    }
+
+    fn get_text(&self, name: &str) -> Option<&str> {
+        match self.get_str(name) {
+            Some(value) => Some(value),
+            _ => self.get_ref::<Name>(name).map(AsRef::as_ref),
+        }
+    }
+
+    fn insert(&mut self, name: Name, value: Value) {
+        let name = self.canonical(name);
+        if !self.protected.contains(&name) {
+            self.overwrite(name, value);
+        }
+    }
+
+    #[cfg(feature = "json")]
+    fn json(&self, prefix: &str, secrets: &HashSet<Name>) -> Json {
+        let members = self.parameters.iter().map(|(name, value)| {
+            let path = format!("{prefix}{}", path::escape_segment(name, '.'));
+            let value = match value.get_ref::<Options>() {
+                _ if secrets.contains(path.as_str()) => Json::String("***".into()),
+                Some(options) => options.json(&format!("{path}."), secrets),
+                _ => types::json(value.as_any()).unwrap_or(Json::Null),
+            };
+            (name.to_string(), value)
+        });
+        Json::Object(members.collect())
+    }
+
+    fn live(&self, name: &str) -> Option<&Value> {
+        let name = self.normalize(name);
+        self.parameters
+            .get(resolve(&self.aliases, &name))
+            .filter(|value| !value.expired())
+    }
+
+    fn merge_one(&mut self, name: Name, value: Value) {
+        match value.type_id == TypeId::of::<Clear>() {
+            true => {
+                self.delete(&name);
+            }
+            _ => {
+                self.insert(name, value);
+            }
+        }
+    }
+
+    fn path_entry_or_insert_with<T: Any, F: FnOnce() -> T>(
+        &mut self,
+        path: &str,
+        initialize: F,
+    ) -> &mut T {
+        let (name, rest) = match path::split_first(path, '.') {
+            (name, Some(rest)) => (name, rest),
+            (name, _) => {
+                if self.get_ref::<T>(&name).is_none() {
+                    let name = self.canonical(Cow::Owned(name.to_string()));
+                    self.overwrite(name, Value::new(initialize()));
+                }
+                return match self.get_mut(&name) {
+                    Some(value) => value,
+                    _ => unreachable!(),
+                };
+            }
+        };
+        if self.get_ref::<Options>(&name).is_none() {
+            let name = self.canonical(Cow::Owned(name.to_string()));
+            self.overwrite(name, Value::new(Options::new()));
+        }
+        match self.get_mut::<Options>(&name) {
+            Some(options) => options.path_entry_or_insert_with(rest, initialize),
+            _ => unreachable!(),
+        }
+    }
+
+    fn normalize<'l>(&self, name: &'l str) -> Cow<'l, str> {
+        match self.normalizer {
+            Some(normalizer) => Cow::Owned(normalizer(name)),
+            _ => Cow::Borrowed(name),
+        }
+    }
+
+    fn overwrite(&mut self, name: Name, mut value: Value) {
+        self.version += 1;
+        if self.timestamps {
+            value.metadata_mut().inserted = Some(Instant::now());
+        } else if let Some(metadata) = &mut value.metadata {
+            metadata.inserted = None;
+        }
+        value.sequence = self.version;
+        value.modified = self.version;
+        match self.checkpoints.last_mut() {
+            Some((_, journal)) if !journal.contains_key(&name) => {
+                let previous = self.parameters.insert(name.clone(), value);
+                journal.insert(name, previous);
+            }
+            _ => {
+                self.parameters.insert(name, value);
+            }
+        }
+    }
+
+    fn detach(&mut self, name: &str) -> Option<(Name, Value)> {
+        let name = self.normalize(name);
+        let name = resolve(&self.aliases, &name);
+        if self.protected.contains(name) {
+            return None;
+        }
+        let entry = self.parameters.remove_entry(name)?;
+        self.version += 1;
+        Some(entry)
+    }
+
+    fn delete(&mut self, name: &str) -> bool {
+        match self.detach(name) {
+            Some((name, value)) => {
+                self.record(&name, Some(value));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn remove(&mut self, name: &str) -> Option<(Name, Value)> {
+        let (name, value) = self.detach(name)?;
+        if let Some((_, journal)) = self.checkpoints.last_mut() {
+            record_copy(journal, &name, &value);
+        }
+        Some((name, value))
+    }
+
+    fn remove_where<F: FnMut(&Name, &mut Value) -> bool>(&mut self, mut f: F) -> Vec<Name> {
+        let protected = &self.protected;
+        let removed = self
+            .parameters
+            .extract_if(|name, value| !protected.contains(name) && f(name, value))
+            .collect::<Vec<_>>();
+        if removed.is_empty() {
+            return Vec::new();
+        }
+        self.version += 1;
+        let mut names = Vec::with_capacity(removed.len());
+        for (name, value) in removed {
+            self.record(&name, Some(value));
+            names.push(name);
+        }
+        names
+    }
+
+    fn render(&self, path: &str, value: &Value) -> Option<String> {
+        let value = value.display()?;
+        match self.secrets.contains(path) {
+            true => Some("***".into()),
+            _ => Some(value),
+        }
+    }
+
+    fn record(&mut self, name: &Name, value: Option<Value>) {
+        if let Some((_, journal)) = self.checkpoints.last_mut() {
+            if !journal.contains_key(name) {
+                journal.insert(name.clone(), value);
+            }
+        }
+    }
+
+    fn record_copies<F: FnMut(&Name, &Value) -> bool>(&mut self, mut f: F) {
+        if let Some((_, journal)) = self.checkpoints.last_mut() {
+            let protected = &self.protected;
+            for (name, value) in &self.parameters {
+                if !protected.contains(name) && f(name, value) {
+                    record_copy(journal, name, value);
+                }
+            }
+        }
+    }
+
+    fn retain_paths_in(&mut self, prefix: &str, f: &mut dyn FnMut(&str) -> bool) {
+        let version = self.version + 1;
+        let protected = &self.protected;
+        let mut journal = self.checkpoints.last_mut().map(|(_, journal)| journal);
+        let mut changed = false;
+        let removed = self.parameters.extract_if(|name, value| {
+            if protected.contains(name) {
+                return false;
+            }
+            if let (Some(journal), Some(_)) = (&mut journal, value.get_ref::<Options>()) {
+                record_copy(journal, name, value);
+            }
+            let path = format!("{prefix}{}", path::escape_segment(name, '.'));
+            let (retain, modified) = match value.get_mut::<Options>() {
+                Some(options) => {
+                    let before = options.version;
+                    options.retain_paths_in(&format!("{path}."), f);
+                    (!options.parameters.is_empty(), options.version != before)
+                }
+                _ => (f(&path), false),
+            };
+            if modified {
+                value.modified = version;
+            }
+            changed |= modified || !retain;
+            !retain
+        });
+        let removed = removed.collect::<Vec<_>>();
+        for (name, value) in removed {
+            self.record(&name, Some(value));
+        }
+        if changed {
+            self.version = version;
+        }
+    }
+
+    fn undo(&mut self, journal: Journal) {
+        if journal.is_empty() {
+            return;
+        }
+        self.version += 1;
+        for (name, value) in journal {
+            if self.protected.contains(&name) {
+                continue;
+            }
+            match value {
+                Some(mut value) => {
+                    value.modified = self.version;
+                    self.parameters.insert(name, value);
+                }
+                _ => {
+                    self.parameters.remove(&name);
+                }
+            }
+        }
+    }
+
+    fn value_mut(&mut self, name: &str) -> Option<&mut Value> {
+        let name = self.normalize(name);
+        let name = resolve(&self.aliases, &name);
+        if let Some((_, journal)) = self.checkpoints.last_mut() {
+            if let Some((name, value)) = self.parameters.get_key_value(name) {
+                record_copy(journal, name, value);
+            }
+        }
+        let value = self.parameters.get_mut(name)?;
+        self.version += 1;
+        value.modified = self.version;
+        Some(value)
+    }
+
+    fn writable(&self, path: &str) -> bool {
+        let (name, rest) = path::split_first(path, '.');
+        let name = self.normalize(&name);
+        let name = resolve(&self.aliases, &name);
+        if self.protected.contains(name) {
+            return false;
+        }
+        match (rest, self.parameters.get(name)) {
+            (Some(rest), Some(value)) => match value.get_ref::<Options>() {
+                Some(options) => options.writable(rest),
+                _ => true,
+            },
+            _ => true,
+        }
+    }
+}
+
+fn glob(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+    let (mut i, mut j) = (0, 0);
+    let mut star = None;
+    while j < name.len() {
+        match pattern.get(i) {
+            Some('*') => {
+                star = Some((i, j));
+                i += 1;
+            }
+            Some(&character) if character == '?' || character == name[j] => {
+                i += 1;
+                j += 1;
+            }
+            _ => match star {
+                Some((k, l)) => {
+                    star = Some((k, l + 1));
+                    i = k + 1;
+                    j = l + 1;
+                }
+                _ => return false,
+            },
+        }
+    }
+    pattern[i..].iter().all(|&character| character == '*')
+}
+
+fn glob_path(pattern: &[Cow<str>], path: &[Cow<str>]) -> bool {
+    match (pattern.split_first(), path.split_first()) {
+        (None, _) => path.is_empty(),
+        (Some((segment, pattern)), _) if segment == "**" => {
+            (0..=path.len()).any(|i| glob_path(pattern, &path[i..]))
+        }
+        (Some((segment, pattern)), Some((name, path))) => {
+            glob(segment, name) && glob_path(pattern, path)
+        }
+        _ => false,
+    }
+}
+
+fn record_copy(journal: &mut Journal, name: &Name, value: &Value) {
+    if !journal.contains_key(name) {
+        if let Some(value) = value.try_clone_all() {
+            journal.insert(name.clone(), Some(value));
+        }
+    }
+}
+
+fn resolve<'l>(aliases: &'l HashMap<Name, Name>, name: &'l str) -> &'l str {
+    match aliases.get(name) {
+        Some(canonical) => canonical,
+        _ => name,
+    }
+}
+
+fn warn(hook: Option<&WarningHook>, message: &str) {
+    if let Some(WarningHook(hook)) = hook {
+        hook(message);
+    }
+}
+
+impl<T> PathKey<T> {
+    /// Create a path.
+    #[inline]
+    pub const fn new(path: &'static str) -> PathKey<T> {
+        PathKey {
+            path,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Return the path.
+    #[inline]
+    pub const fn path(&self) -> &'static str {
+        self.path
+    }
+}
+
+impl<T> Clone for PathKey<T> {
+    #[inline]
+    fn clone(&self) -> PathKey<T> {
+        *self
+    }
+}
+
+impl<T> Copy for PathKey<T> {}
+
+impl<T> std::fmt::Debug for PathKey<T> {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter.debug_tuple("PathKey").field(&self.path).finish()
+    }
+}
+
+impl std::fmt::Debug for WarningHook {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter.write_str("WarningHook")
+    }
+}
+
+impl KeyPolicy {
+    /// Check a name.
+    pub fn check(&self, name: &str) -> Result<(), KeyError> {
+        if self.non_empty && name.is_empty() {
+            return Err(KeyError::Empty);
+        }
+        if let Some(max_length) = self.max_length {
+            if name.len() > max_length {
+                return Err(KeyError::TooLong {
+                    length: name.len(),
+                    max_length,
+                });
+            }
+        }
+        if let Some(allowed) = self.allowed {
+            if let Some(character) = name.chars().find(|&character| !allowed(character)) {
+                return Err(KeyError::InvalidCharacter(character));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for KeyError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyError::Empty => write!(formatter, "the name is empty"),
+            KeyError::TooLong { length, max_length } => write!(
+                formatter,
+                "the name is {length} bytes long, which exceeds {max_length}",
+            ),
+            KeyError::InvalidCharacter(character) => {
+                write!(
+                    formatter,
+                    "the name contains {character:?}, which is not allowed"
+                )
+            }
+            KeyError::Protected => write!(formatter, "the name is protected"),
+        }
+    }
+}
+
+impl std::error::Error for KeyError {}
+
+impl std::fmt::Display for GetError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GetError::Missing => write!(formatter, "the parameter is missing"),
+            GetError::Mismatch { expected, actual } => {
+                write!(
+                    formatter,
+                    "the parameter is of type {actual}, not {expected}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for GetError {}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            formatter,
+            "the override {} is not of the form name=value",
+            self.index,
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl<E: std::fmt::Display> std::fmt::Display for ConvertError<E> {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConvertError::Get(error) => error.fmt(formatter),
+            ConvertError::Convert(error) => write!(formatter, "the conversion failed: {error}"),
+        }
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for ConvertError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConvertError::Get(error) => Some(error),
+            ConvertError::Convert(error) => Some(error),
+        }
+    }
+}
+
+impl Value {
+    /// Create a value.
+    #[inline]
+    pub fn new<T: Any>(value: T) -> Value {
+        Value {
+            inner: Box::new(value),
+            type_id: TypeId::of::<T>(),
+            type_name: std::any::type_name::<T>(),
+            access: None,
+            sequence: 0,
+            modified: 0,
+            metadata: None,
+        }
+    }
+
+    /// Create a value shared with other values.
+    ///
+    /// The value is read through the `Rc` and is thus the same allocation for
+    /// all the values created from clones of the `Rc`. Mutable access clones
+    /// the contents first if the `Rc` is not unique, so that the other values
+    /// are unaffected.
+    pub fn shared<T: Any + Clone>(value: Rc<T>) -> Value {
+        Value {
+            inner: Box::new(value),
+            type_id: TypeId::of::<T>(),
+            type_name: std::any::type_name::<T>(),
+            access: Some(
+                const {
+                    &Access {
+                        get: shared_get::<T>,
+                        get_mut: shared_get_mut::<T>,
+                        into_inner: shared_into_inner::<T>,
+                        clone: Some(shared_clone::<T>),
+                    }
+                },
+            ),
+            sequence: 0,
+            modified: 0,
+            metadata: None,
+        }
+    }
+
+    /// Create a value from a boxed one.
+    ///
+    /// Since the concrete type is unknown, the type name is available only for
+    /// registered types (see `Options::bytes_estimate`) and is `"dyn Any"`
+    /// otherwise.
+    pub fn from_boxed(value: Box<dyn Any>) -> Value {
+        Value {
+            type_id: (*value).type_id(),
+            type_name: types::name(&*value).unwrap_or("dyn Any"),
+            inner: value,
+            access: None,
+            sequence: 0,
+            modified: 0,
+            metadata: None,
+        }
+    }
+
+    /// Get the value.
+    #[inline]
+    pub fn get<T: Any + Clone>(&self) -> Option<T> {
+        self.get_ref().cloned()
+    }
+
+    /// Get a reference to the value.
+    #[inline]
+    pub fn get_ref<T: Any>(&self) -> Option<&T> {
+        self.as_any().downcast_ref()
+    }
+
+    /// Get a mutable reference to the value.
+    #[inline]
+    pub fn get_mut<T: Any>(&mut self) -> Option<&mut T> {
+        self.as_any_mut().downcast_mut()
+    }
+
+    /// Get a type-erased reference to the value.
+    #[inline]
+    pub fn as_any(&self) -> &dyn Any {
+        match self.access {
+            Some(access) => (access.get)(&*self.inner),
+            _ => &*self.inner,
+        }
+    }
+
+    /// Get a type-erased mutable reference to the value.
+    #[inline]
+    pub fn as_any_mut(&mut self) -> &mut dyn Any {
+        match self.access {
+            Some(access) => (access.get_mut)(&mut *self.inner),
+            _ => &mut *self.inner,
+        }
+    }
+
+    /// Convert into the boxed value.
+    #[inline]
+    pub fn into_boxed(self) -> Box<dyn Any> {
+        match self.access {
+            Some(access) => (access.into_inner)(self.inner),
+            _ => self.inner,
+        }
+    }
+
+    /// Clone the value if it is of a registered type.
+    ///
+    /// The registered types are listed in `Options::bytes_estimate`; `Json` is
+    /// also supported. Nested options are cloned with all the values that can
+    /// be cloned, and the others are skipped. Shared values (see `shared`) are
+    /// cloned by cloning the `Rc`, and the clones remain shared.
+    pub fn try_clone(&self) -> Option<Value> {
+        let inner = match self.access {
+            Some(&Access {
+                clone: Some(clone), ..
+            }) => {
+                return Some(Value {
+                    inner: clone(&*self.inner),
+                    metadata: self.metadata.clone(),
+                    ..*self
+                })
+            }
+            _ => match self.get_ref::<Options>() {
+                Some(options) => Box::new(options.clone_cloneable()),
+                _ => types::clone(self.as_any())?,
+            },
+        };
+        Some(Value {
+            inner,
+            type_id: self.type_id,
+            type_name: self.type_name,
+            access: None,
+            sequence: self.sequence,
+            modified: self.modified,
+            metadata: self.metadata.clone(),
+        })
+    }
+
+    /// Render the value as a string if it is of a registered type.
+    ///
+    /// The registered types are listed in `Options::bytes_estimate`.
+    #[inline]
+    pub fn display(&self) -> Option<String> {
+        types::display(self.as_any())
+    }
+
+    /// Return the identifier of the type.
+    #[inline]
+    pub fn type_id(&self) -> TypeId {
+        self.type_id
+    }
+
+    /// Return the name of the type.
+    #[inline]
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    fn expired(&self) -> bool {
+        let expires = self.metadata.as_ref().and_then(|metadata| metadata.expires);
+        expires.is_some_and(|expires| expires <= Instant::now())
+    }
+
+    // Clone the value unless it is or contains a value that cannot be cloned.
+    fn try_clone_all(&self) -> Option<Value> {
+        let value = self.try_clone()?;
+        match (self.get_ref::<Options>(), value.get_ref::<Options>()) {
+            (Some(one), Some(other)) if one.walk_leaves().len() != other.walk_leaves().len() => {
+                None
+            }
+            _ => Some(value),
+        }
+    }
+
+    fn inserted(&self) -> Option<Instant> {
+        self.metadata.as_ref()?.inserted
+    }
+
+    fn metadata_mut(&mut self) -> &mut Metadata {
+        self.metadata.get_or_insert_with(Default::default)
+    }
+
+    fn downcast<T: Any>(self) -> Result<T, Value> {
+        if self.type_id != TypeId::of::<T>() {
+            return Err(self);
+        }
+        match self.into_boxed().downcast::<T>() {
+            Ok(value) => Ok(*value),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Set the value.
+    #[inline]
+    pub fn set<T: Any>(&mut self, value: T) {
+        self.replace(value);
+    }
+
+    /// Set the value and return the previous one.
+    ///
+    /// The metadata, such as the time of insertion, the expiration, and the
+    /// source recorded by `Options::merge_tracked`, is kept.
+    #[inline]
+    pub fn replace<T: Any>(&mut self, value: T) -> Box<dyn Any> {
+        self.swap(Value::new(value)).into_boxed()
+    }
+
+    fn swap(&mut self, mut value: Value) -> Value {
+        value.sequence = self.sequence;
+        value.modified = self.modified;
+        value.metadata = self.metadata.take();
+        std::mem::replace(self, value)
+    }
+}
+
+impl<T: Any> Lazy<T> {
+    fn force(&self) -> &T {
+        self.value.get_or_init(|| match self.initialize.take() {
+            Some(initialize) => initialize(),
+            _ => unreachable!(),
+        })
+    }
+
+    fn get(value: &dyn Any) -> &dyn Any {
+        match value.downcast_ref::<Lazy<T>>() {
+            Some(value) => value.force(),
+            _ => unreachable!(),
+        }
+    }
+
+    fn get_mut(value: &mut dyn Any) -> &mut dyn Any {
+        match value.downcast_mut::<Lazy<T>>() {
+            Some(value) => {
+                value.force();
+                match value.value.get_mut() {
+                    Some(value) => value,
+                    _ => unreachable!(),
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn into_inner(value: Box<dyn Any>) -> Box<dyn Any> {
+        match value.downcast::<Lazy<T>>() {
+            Ok(value) => {
+                value.force();
+                match value.value.into_inner() {
+                    Some(value) => Box::new(value),
+                    _ => unreachable!(),
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+fn shared_get<T: Any>(value: &dyn Any) -> &dyn Any {
+    match value.downcast_ref::<Rc<T>>() {
+        Some(value) => &**value,
+        _ => unreachable!(),
+    }
+}
+
+fn shared_clone<T: Any>(value: &dyn Any) -> Box<dyn Any> {
+    match value.downcast_ref::<Rc<T>>() {
+        Some(value) => Box::new(value.clone()),
+        _ => unreachable!(),
+    }
+}
+
+fn shared_get_mut<T: Any + Clone>(value: &mut dyn Any) -> &mut dyn Any {
+    match value.downcast_mut::<Rc<T>>() {
+        Some(value) => Rc::<T>::make_mut(value),
+        _ => unreachable!(),
+    }
+}
+
+fn shared_into_inner<T: Any + Clone>(value: Box<dyn Any>) -> Box<dyn Any> {
+    match value.downcast::<Rc<T>>() {
+        Ok(value) => Box::new(Rc::try_unwrap(*value).unwrap_or_else(|value| (*value).clone())),
+        _ => unreachable!(),
+    }
+}
+
+impl<'l, S: BuildHasher> ScopedOptions<'l, S> {
+    /// Set the value of a parameter.
+    pub fn set<T: Into<String>, U: Any>(&mut self, name: T, value: U) -> &mut ScopedOptions<'l, S> {
+        self.options
+            .insert(Cow::Owned(name.into()), Value::new(value));
+        self
+    }
+
+    /// Remove a parameter and return whether it was present.
+    #[inline]
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.options.delete(name)
+    }
+}
+
+impl FrozenOptions {
+    /// Get the value of a parameter.
+    #[inline]
+    pub fn get<T: Any + Clone>(&self, name: &str) -> Option<T> {
+        self.get_ref(name).cloned()
+    }
+
+    /// Get a reference to the value of a parameter.
+    #[inline]
+    pub fn get_ref<T: Any>(&self, name: &str) -> Option<&T> {
+        self.parameters.get(name)?.downcast_ref()
+    }
+
+    /// Get the value of a nested parameter.
+    ///
+    /// See `Options::get_path`.
+    #[inline]
+    pub fn get_path<T: Any + Clone>(&self, path: &str) -> Option<T> {
+        self.get_path_ref(path).cloned()
+    }
+
+    /// Get a reference to the value of a nested parameter.
+    pub fn get_path_ref<T: Any>(&self, path: &str) -> Option<&T> {
+        match path::split_last(path, '.') {
+            (Some(parent), name) => self.get_path_ref::<FrozenOptions>(parent)?.get_ref(&name),
+            (_, name) => self.get_ref(&name),
+        }
+    }
+
+    /// Check the presence of a parameter.
+    #[inline]
+    pub fn has(&self, name: &str) -> bool {
+        self.parameters.contains_key(name)
+    }
+
+    /// Return an iterator over names.
+    #[inline]
+    pub fn names(&self) -> impl Iterator<Item = &Name> {
+        self.parameters.keys()
+    }
+}
+
+impl<'l, S: BuildHasher> Overlay<'l, S> {
+    /// Get the value of a parameter.
+    #[inline]
+    pub fn get<T: Any + Clone>(&self, name: &str) -> Option<T> {
+        self.get_ref(name).cloned()
+    }
+
+    /// Get a reference to the value of a parameter.
+    ///
+    /// If the top options have a parameter with the name, the base options are
+    /// not consulted even if the value is of a different type.
+    pub fn get_ref<T: Any>(&self, name: &str) -> Option<&'l T> {
+        match self.top.live(name) {
+            Some(value) => value.get_ref(),
+            _ => self.base.get_ref(name),
+        }
+    }
+
+    /// Check the presence of a parameter in either of the two options.
+    #[inline]
+    pub fn has(&self, name: &str) -> bool {
+        self.top.has(name) || self.base.has(name)
+    }
+
+    /// Return an iterator over the names present in either of the two options.
+    ///
+    /// Each name is yielded once.
+    pub fn names(&self) -> impl Iterator<Item = &'l Name> {
+        let top = self.top;
+        top.names().chain(self.base.key_difference(top))
+    }
+}
+
+impl<'l, S: BuildHasher> Namespace<'l, S> {
+    /// Get the value of a parameter.
+    #[inline]
+    pub fn get<T: Any + Clone>(&self, name: &str) -> Option<T> {
+        self.get_ref(name).cloned()
+    }
+
+    /// Get a reference to the value of a parameter.
+    #[inline]
+    pub fn get_ref<T: Any>(&self, name: &str) -> Option<&'l T> {
+        self.options.get_ref(&format!("{}{name}", self.prefix))
+    }
+
+    /// Check the presence of a parameter.
+    #[inline]
+    pub fn has(&self, name: &str) -> bool {
+        self.options.has(&format!("{}{name}", self.prefix))
+    }
+
+    /// Return an iterator over the names with the prefix stripped.
+    pub fn names(&self) -> impl Iterator<Item = &'l str> {
+        let prefix = self.prefix;
+        self.options
+            .names()
+            .filter_map(move |name| name.strip_prefix(prefix))
+    }
+}
+
+impl<'l, S: BuildHasher> NamespaceMut<'l, S> {
+    /// Get the value of a parameter.
+    #[inline]
+    pub fn get<T: Any + Clone>(&self, name: &str) -> Option<T> {
+        self.options.get(&format!("{}{name}", self.prefix))
+    }
+
+    /// Check the presence of a parameter.
+    #[inline]
+    pub fn has(&self, name: &str) -> bool {
+        self.options.has(&format!("{}{name}", self.prefix))
+    }
+
+    /// Set the value of a parameter.
+    #[inline]
+    pub fn set<U: Any>(&mut self, name: &str, value: U) -> &mut NamespaceMut<'l, S> {
+        self.options.set(format!("{}{name}", self.prefix), value);
+        self
+    }
+}
+
+impl<'l, S: BuildHasher> PathEntry<'l, S> {
+    /// Modify the value if it is present and of the given type.
+    #[inline]
+    pub fn and_modify<T: Any, F: FnOnce(&mut T)>(self, modify: F) -> PathEntry<'l, S> {
+        if let Some(value) = self.options.get_path_mut(self.path) {
+            modify(value);
+        }
+        self
+    }
+
+    /// Get a mutable reference to the value inserting a default first.
+    ///
+    /// The default is inserted when the value is absent or of a different type,
+    /// and the nested options are treated as in `Options::path_entry_or_insert`.
+    #[inline]
+    pub fn or_insert<T: Any>(self, default: T) -> &'l mut T {
+        self.options
+            .path_entry_or_insert_with(self.path, || default)
+    }
+
+    /// Get a mutable reference to the value inserting a computed default first.
+    ///
+    /// The function is called only if the default is needed, and the nested
+    /// options are treated as in `Options::path_entry_or_insert`.
+    #[inline]
+    pub fn or_insert_with<T: Any, F: FnOnce() -> T>(self, initialize: F) -> &'l mut T {
+        self.options
+            .path_entry_or_insert_with(self.path, initialize)
+    }
+}
+
+impl<S: BuildHasher> Deref for ScopedOptions<'_, S> {
+    type Target = Options<S>;
+
+    #[inline]
+    fn deref(&self) -> &Options<S> {
+        self.options
+    }
+}
+
+impl<S: BuildHasher> Drop for ScopedOptions<'_, S> {
+    fn drop(&mut self) {
+        if let Some((_, journal)) = self.options.checkpoints.pop() {
+            self.options.undo(journal);
+        }
+    }
+}
+
+impl<S: BuildHasher + Default> Default for Options<S> {
+    #[inline]
+    fn default() -> Options<S> {
+        Options::with_hasher(S::default())
+    }
+}
+
+impl<'l, S: BuildHasher> IntoIterator for &'l Options<S> {
+    type Item = (&'l Name, &'l Value);
+    type IntoIter = Parameters<'l>;
+
+    #[inline]
+    fn into_iter(self) -> Parameters<'l> {
+        self.iter()
+    }
+}
+
+impl<'l, S: BuildHasher> IntoIterator for &'l mut Options<S> {
+    type Item = (&'l Name, &'l mut Value);
+    type IntoIter = ParametersMut<'l>;
+
+    #[inline]
+    fn into_iter(self) -> ParametersMut<'l> {
+        self.iter_mut()
+    }
+}
+
+impl<'l> Iterator for Parameters<'l> {
+    type Item = (&'l Name, &'l Value);
+
+    #[inline]
+    fn next(&mut self) -> Option<(&'l Name, &'l Value)> {
+        self.iterator.next()
+    }
+}
+
+impl<'l> Iterator for ParametersMut<'l> {
+    type Item = (&'l Name, &'l mut Value);
+
+    #[inline]
+    fn next(&mut self) -> Option<(&'l Name, &'l mut Value)> {
+        let (name, value) = self.iterator.next()?;
+        value.modified = self.version;
+        Some((name, value))
+    }
+}
+
+impl Drop for Drain<'_> {
+    #[inline]
+    fn drop(&mut self) {
+        self.iterator.by_ref().for_each(drop);
+    }
+}
+
+impl Iterator for Drain<'_> {
+    type Item = (Name, Value);
+
+    #[inline]
+    fn next(&mut self) -> Option<(Name, Value)> {
+        self.iterator.next()
+    }
+}
+
+impl<'l> Iterator for Names<'l> {
+    type Item = &'l Name;
+
+    #[inline]
+    fn next(&mut self) -> Option<&'l Name> {
+        self.iterator.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::Options;
+
+    #[test]
+    fn bytes_estimate() {
+        let mut options = Options::new();
+        let empty = options.bytes_estimate();
+
+        options.set("a", 42i32);
+        let scalar = options.bytes_estimate();
+        assert!(scalar >= empty + 1 + 4);
+
+        options.set("b", String::with_capacity(1000));
+        assert!(options.bytes_estimate() >= scalar + 1 + 1000);
+
+        let mut nested = Options::new();
+        nested.set("c", vec![0u8; 1000]);
+        let before = options.bytes_estimate();
+        options.set("d", nested);
+        assert!(options.bytes_estimate() >= before + 1 + 1000);
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn from_json() {
+        use super::Json;
+
+        let json = r#"{
+            "a": {"b": {"c": 42}, "d": true, "e": null},
+            "f": [1, 2.5],
+            "g": [1, 2],
+            "h": ["x", "y"],
+            "i": [1, "x"],
+            "j": [],
+            "k": 9223372036854775808
+        }"#;
+        let json = json.parse::<Json>().unwrap();
+        let options = Options::from_json(&json);
+        assert_eq!(options.get_path::<i64>("a.b.c"), Some(42));
+        assert_eq!(options.get_path::<bool>("a.d"), Some(true));
+        assert!(!options.get_ref::<Options>("a").unwrap().has("e"));
+        assert_eq!(options.get::<Vec<f64>>("f"), Some(vec![1.0, 2.5]));
+        assert_eq!(options.get::<Vec<i64>>("g"), Some(vec![1, 2]));
+        assert_eq!(
+            options.get::<Vec<String>>("h"),
+            Some(vec!["x".to_string(), "y".to_string()]),
+        );
+        assert_eq!(
+            options.get::<Vec<Json>>("i"),
+            Some(vec![Json::Integer(1), Json::String("x".to_string())]),
+        );
+        assert_eq!(options.get::<Vec<Json>>("j"), Some(vec![]));
+        assert_eq!(options.get::<f64>("k"), Some(9223372036854775808.0));
+        let mut expected = json.clone();
+        if let Json::Object(members) = &mut expected {
+            if let Some(Json::Object(members)) = members.get_mut("a") {
+                members.remove("e");
+            }
+            members.insert(
+                "f".to_string(),
+                Json::Array(vec![Json::Float(1.0), Json::Float(2.5)]),
+            );
+        }
+        assert_eq!(options.to_json(), expected);
+        assert!(Options::from_json(&Json::Bool(true))
+            .names()
+            .next()
+            .is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "toml")]
+    fn from_toml_str() {
+        use super::{Json, TomlError};
+
+        let text = r#"
+            name = "server"
+            ports = [8080, 8081]
+
+            [database]
+            host = "localhost"
+            started = 1979-05-27T07:32:00Z
+
+            [[users]]
+            id = 1
+        "#;
+        let options = Options::from_toml_str(text).unwrap();
+        assert_eq!(options.get_ref::<String>("name").unwrap(), "server");
+        assert_eq!(options.get::<Vec<i64>>("ports"), Some(vec![8080, 8081]));
+        assert_eq!(
+            options.get_path::<String>("database.host").as_deref(),
+            Some("localhost"),
+        );
+        assert_eq!(
+            options.get_path::<String>("database.started").as_deref(),
+            Some("1979-05-27T07:32:00Z"),
+        );
+        assert_eq!(
+            options.get::<Vec<Json>>("users"),
+            Some(vec![Json::Object(
+                [("id".to_string(), Json::Integer(1))].into()
+            )]),
+        );
+        assert_eq!(
+            Options::from_toml_str("a = 1\na = 2").err(),
+            Some(TomlError::Invalid { line: 2 }),
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "toml")]
+    fn to_toml_string() {
+        use super::TomlError;
+
+        let text = r#"
+            name = "server"
+            ratio = 0.5
+
+            [database]
+            host = "localhost"
+            ports = [5432, 5433]
+            started = 1979-05-27T07:32:00Z
+
+            [database.pool]
+            size = 4
+
+            [[users]]
+            id = 1
+        "#;
+        let options = Options::from_toml_str(text).unwrap();
+        let text = options.to_toml_string().unwrap();
+        assert_eq!(
+            Options::from_toml_str(&text).unwrap().to_json(),
+            options.to_json(),
+        );
+        let mut options = setup_nested();
+        options
+            .get_mut::<Options>("a")
+            .unwrap()
+            .set("e", std::time::Duration::ZERO);
+        assert_eq!(
+            options.to_toml_string(),
+            Err(TomlError::Unsupported {
+                path: "a.e".to_string()
+            }),
+        );
+    }
+
+    #[test]
+    fn read_properties() {
+        let text = "# Database\n\
+                    db.host = localhost\n\
+                    \n\
+                    db.port=5432\n\
+                    \\#c=\\ padded\\ \n\
+                    b=x\\=1\\ny\\=2\n\
+                    empty=\n";
+        let options = Options::read_properties(text.as_bytes()).unwrap();
+        let mut names = options.names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, &["#c", "b", "db.host", "db.port", "empty"]);
+        assert_eq!(options.get::<String>("db.host").unwrap(), "localhost");
+        assert_eq!(options.get::<String>("db.port").unwrap(), "5432");
+        assert_eq!(options.get::<String>("#c").unwrap(), " padded ");
+        assert_eq!(options.get::<String>("b").unwrap(), "x=1\ny=2");
+        assert_eq!(options.get::<String>("empty").unwrap(), "");
+
+        let mut buffer = Vec::new();
+        options.write_properties(&mut buffer).unwrap();
+        let other = Options::read_properties(&buffer[..]).unwrap();
+        assert_eq!(other.display_map(), options.display_map());
+
+        let error = Options::read_properties("a=1\nb\n".as_bytes()).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "line 2 has no equals sign");
+    }
+
+    #[test]
+    fn from_layers() {
+        let mut defaults = Options::new();
+        defaults
+            .set("host", "localhost")
+            .set("port", 80)
+            .set("debug", false);
+        let mut file = Options::new();
+        file.set("port", 8080).set("user", "admin");
+        let mut arguments = Options::new();
+        arguments.set("port", 9090).set("debug", true);
+        let options = Options::from_layers([defaults, file, arguments]);
+        assert_eq!(options.get::<&str>("host"), Some("localhost"));
+        assert_eq!(options.get::<i32>("port"), Some(9090));
+        assert_eq!(options.get::<bool>("debug"), Some(true));
+        assert_eq!(options.get::<&str>("user"), Some("admin"));
+        assert_eq!(options.names().count(), 4);
+    }
+
+    #[test]
+    fn from_str_pairs() {
+        let pairs = vec![
+            ("a".to_string(), "42".to_string()),
+            ("b".to_string(), "true".to_string()),
+        ];
+        let options = Options::from_str_pairs(pairs);
+        assert_eq!(options.get::<String>("a").unwrap(), "42");
+        assert_eq!(options.get::<String>("b").unwrap(), "true");
+        assert_eq!(options.get::<&str>("a"), None);
+    }
+
+    #[test]
+    fn get() {
+        let options = setup();
+
+        macro_rules! test(
+            ($name:expr, $value:expr, $kind:ty) => (
+                assert_eq!(options.get::<$kind>($name).unwrap(), $value)
+            );
+        );
+
+        test!("a", 42, i32);
+        test!("b", true, bool);
+        test!("c", "Hi, there!", &str);
+        test!("d", "Hello, world!".to_string(), String);
+        test!("e", vec![4u8, 2u8], Vec<u8>);
+    }
+
+    #[test]
+    fn get_or_env() {
+        let options = setup();
+        assert_eq!(
+            options.get_or_env("d", "CARGO_PKG_NAME"),
+            Some("Hello, world!".to_string()),
+        );
+        assert_eq!(
+            options.get_or_env("z", "CARGO_PKG_NAME"),
+            Some(env!("CARGO_PKG_NAME").to_string()),
+        );
+        assert_eq!(
+            options.get_or_env("a", "CARGO_PKG_NAME"),
+            Some(env!("CARGO_PKG_NAME").to_string()),
+        );
+        assert_eq!(options.get_or_env("z", "OPTIONS_GET_OR_ENV_UNSET"), None);
+    }
+
+    #[test]
+    fn get_array() {
+        let mut options = Options::new();
+        options.set("a", [1u8, 2, 3, 4]);
+        assert_eq!(options.get_array::<u8, 4>("a"), Some([1, 2, 3, 4]));
+        assert_eq!(options.get_array::<u8, 3>("a"), None);
+        assert_eq!(options.get_array::<i8, 4>("a"), None);
+    }
+
+    #[test]
+    fn get_in_range() {
+        let mut options = Options::new();
+        options.set("a", 0.5f64).set("b", -0.5f64).set("c", 1.5f64);
+        assert_eq!(options.get_in_range("a", 0.0, 1.0), Some(0.5));
+        assert_eq!(options.get_in_range("b", 0.0, 1.0), None);
+        assert_eq!(options.get_in_range("c", 0.0, 1.0), None);
+        assert_eq!(options.get_in_range("c", 0.0, 1.5), Some(1.5));
+        assert_eq!(options.get_in_range("a", 0i32, 1i32), None);
+    }
+
+    #[test]
+    fn get_range() {
+        let mut options = Options::new();
+        options.set("a", 0..10).set("b", 0..=10);
+        assert_eq!(options.get_range("a"), Some(0..10));
+        assert_eq!(options.get_range::<u8>("a"), None);
+        assert_eq!(options.get_range::<i32>("b"), None);
+    }
+
+    #[test]
+    fn get_flags() {
+        let mut options = setup();
+        options
+            .set("f", 0b001u32)
+            .set("g", 0b010u32)
+            .set("h", 0b100u32);
+        assert_eq!(options.get_flags::<u32>(&["f", "g", "h"]), Some(0b111));
+        assert_eq!(options.get_flags::<u32>(&["f", "a", "z"]), Some(0b001));
+        assert_eq!(options.get_flags::<u32>(&["a", "z"]), None);
+        assert_eq!(options.get_flags::<bool>(&["b", "z"]), Some(true));
+    }
+
+    #[test]
+    fn checked_get() {
+        let mut options = setup();
+        let captured = capture(&mut options);
+        assert_eq!(options.checked_get::<i32>("a"), Some(42));
+        assert_eq!(options.checked_get::<i32>("z"), None);
+        assert!(captured().is_empty());
+        assert_eq!(options.checked_get::<bool>("a"), None);
+        assert_eq!(
+            captured(),
+            &["the parameter \"a\" is of type i32, not bool"],
+        );
+    }
+
+    #[test]
+    fn get_or_warn() {
+        let mut options = setup();
+        let captured = capture(&mut options);
+        assert_eq!(options.get_or_warn("a", 0), 42);
+        assert!(captured().is_empty());
+        assert!(!options.get_or_warn("a", false));
+        assert_eq!(options.get_or_warn("z", 0), 0);
+        assert_eq!(
+            captured(),
+            &[
+                "the parameter \"a\" is of type i32, not bool, and the default is used",
+                "the parameter \"z\" is missing, and the default is used",
+            ],
+        );
+    }
+
+    #[test]
+    fn collect_type() {
+        let mut options = setup();
+        options.set("f", "Bye, world!".to_string());
+        let mut values = options.collect_type::<String>();
+        values.sort();
+        assert_eq!(values, &["Bye, world!", "Hello, world!"]);
+        assert!(options.collect_type::<u64>().is_empty());
+    }
+
+    #[test]
+    fn get_duration() {
+        let mut options = Options::new();
+        options
+            .set("a", Duration::from_secs(2))
+            .set("b", "1500ms")
+            .set("c", "5m".to_string())
+            .set("d", "soon")
+            .set("e", 42);
+        assert_eq!(options.get_duration("a"), Some(Duration::from_secs(2)));
+        assert_eq!(options.get_duration("b"), Some(Duration::from_millis(1500)));
+        assert_eq!(options.get_duration("c"), Some(Duration::from_secs(300)));
+        assert_eq!(options.get_duration("d"), None);
+        assert_eq!(options.get_duration("e"), None);
+        assert_eq!(options.get_duration("z"), None);
+    }
+
+    #[test]
+    fn get_bytes() {
+        let mut options = setup();
+        options.set("f", "0aFF".to_string()).set("g", "0xff");
+        assert_eq!(options.get_bytes("e"), Some(vec![4, 2]));
+        assert_eq!(options.get_bytes("f"), Some(vec![0x0a, 0xff]));
+        assert_eq!(options.get_bytes("g"), None);
+        assert_eq!(options.get_bytes("a"), None);
+        assert_eq!(options.get_bytes("z"), None);
+    }
+
+    #[test]
+    fn get_socket_addr() {
+        use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+        let address = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 8080);
+        let mut options = Options::new();
+        options
+            .set("a", address)
+            .set("b", "127.0.0.1:8080")
+            .set("c", "127.0.0.1".to_string())
+            .set("d", "localhost:8080");
+        assert_eq!(options.get_socket_addr("a"), Some(address));
+        assert_eq!(options.get_socket_addr("b"), Some(address));
+        assert_eq!(options.get_socket_addr("c"), None);
+        assert_eq!(options.get_socket_addr("d"), None);
+        assert_eq!(options.get_ip_addr("c"), Some(address.ip()));
+        assert_eq!(options.get_ip_addr("b"), None);
+    }
+
+    #[test]
+    fn get_ip_list() {
+        use std::net::{IpAddr, Ipv4Addr};
+
+        let addresses = vec![
+            IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)),
+            IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8)),
+        ];
+        let mut options = Options::new();
+        options
+            .set("a", addresses.clone())
+            .set("b", "1.1.1.1,8.8.8.8")
+            .set("c", "1.1.1.1, localhost".to_string())
+            .set("d", "");
+        assert_eq!(options.get_ip_list("a"), Some(addresses.clone()));
+        assert_eq!(options.get_ip_list("b"), Some(addresses));
+        assert_eq!(options.get_ip_list("c"), None);
+        assert_eq!(options.get_ip_list("d"), Some(vec![]));
+        assert_eq!(options.get_ip_list("z"), None);
+    }
+
+    #[test]
+    fn get_char() {
+        let mut options = Options::new();
+        options
+            .set("a", ';')
+            .set("b", "\t")
+            .set("c", "é".to_string())
+            .set("d", "ab")
+            .set("e", "");
+        assert_eq!(options.get_char("a"), Some(';'));
+        assert_eq!(options.get_char("b"), Some('\t'));
+        assert_eq!(options.get_char("c"), Some('é'));
+        assert_eq!(options.get_char("d"), None);
+        assert_eq!(options.get_char("e"), None);
+        assert_eq!(options.get_char("z"), None);
+    }
+
+    #[test]
+    fn get_nonzero() {
+        use std::num::{NonZeroI64, NonZeroU32, NonZeroUsize};
+
+        let mut options = Options::new();
+        options.set("a", 4u32).set("b", 0usize).set("c", -1i64);
+        assert_eq!(options.get_nonzero("a"), NonZeroU32::new(4));
+        assert_eq!(options.get_nonzero::<NonZeroUsize>("b"), None);
+        assert_eq!(options.get_nonzero("c"), NonZeroI64::new(-1));
+        assert_eq!(options.get_nonzero::<NonZeroUsize>("a"), None);
+        assert_eq!(options.get_nonzero::<NonZeroU32>("z"), None);
+    }
+
+    #[test]
+    fn get_list() {
+        let mut options = Options::new();
+        options
+            .set("a", vec!["x, y".to_string()])
+            .set("b", "x,y,z")
+            .set("c", " x ;; y; ".to_string())
+            .set("d", 42);
+        assert_eq!(options.get_list("a", ',').unwrap(), &["x, y"]);
+        assert_eq!(options.get_list("b", ',').unwrap(), &["x", "y", "z"]);
+        assert_eq!(options.get_list("c", ';').unwrap(), &["x", "y"]);
+        assert_eq!(options.get_list("d", ','), None);
+        assert_eq!(options.get_list("z", ','), None);
+    }
+
+    #[test]
+    fn get_fold() {
+        let mut options = Options::new();
+        options.set("foo", 42);
+        assert_eq!(options.get_fold::<i32>("foo"), Some(42));
+        assert_eq!(options.get_fold::<i32>("Foo"), Some(42));
+        assert_eq!(options.get_fold::<i32>("FOO"), Some(42));
+        assert_eq!(options.get_fold::<i32>("bar"), None);
+        assert_eq!(options.get::<i32>("Foo"), None);
+    }
+
+    #[test]
+    fn get_path() {
+        let mut options = setup_nested();
+        assert_eq!(options.get_path::<i32>("a.b.c"), Some(42));
+        assert_eq!(options.get_path::<bool>("a.d"), Some(true));
+        assert_eq!(options.get_path::<i32>("a.b.z"), None);
+        assert_eq!(options.get_path::<i32>("a.d.c"), None);
+        assert_eq!(options.get_path::<i32>("z.b.c"), None);
+
+        *options.get_path_mut::<i32>("a.b.c").unwrap() = 24;
+        assert_eq!(options.get_path::<i32>("a.b.c"), Some(24));
+        assert!(options.get_path_mut::<i32>("a.z.c").is_none());
+
+        options.set("x.y", 1);
+        *options.path_entry_or_insert::<i32>(r"a.b\.c") = 2;
+        assert_eq!(options.get_path::<i32>(r"x\.y"), Some(1));
+        assert_eq!(options.get_path::<i32>(r"a.b\.c"), Some(2));
+        assert_eq!(options.path_display(r"a.b\.c").as_deref(), Some("2"));
+        assert!(options.has_path(r"x\.y") && !options.has_path("x.y"));
+        assert_eq!(options.apply_overrides([r"a.b\.c=3".into()]), Ok(1));
+        assert_eq!(options.path_str(r"a.b\.c"), Some("3"));
+        assert_eq!(options.take_path::<String>(r"a.b\.c").as_deref(), Some("3"));
+        assert_eq!(options.get_path::<i32>("a.b.c"), Some(24));
+    }
+
+    #[test]
+    fn get_path_all() {
+        let mut servers = Options::new();
+        for (name, port) in [("a", 1u16), ("b", 2), ("c", 3)] {
+            let mut server = Options::new();
+            server.set("port", port).set("host", name);
+            servers.set(name, server);
+        }
+        servers.set("d", 4u16);
+        let mut options = Options::new();
+        options.set("servers", servers);
+
+        let mut values = options.get_path_all::<u16>("servers.*.port");
+        values.sort();
+        assert_eq!(
+            values,
+            &[
+                ("servers.a.port".to_string(), 1),
+                ("servers.b.port".to_string(), 2),
+                ("servers.c.port".to_string(), 3),
+            ],
+        );
+        assert_eq!(options.get_path_all::<u16>("servers.*").len(), 1);
+        options
+            .path_entry_or_insert::<Options>("servers")
+            .set("e.f", 5u16);
+        assert_eq!(
+            options.get_path_all::<u16>(r"servers.e\.f"),
+            &[(r"servers.e\.f".to_string(), 5)],
+        );
+        assert_eq!(options.get_path_all::<u16>("servers.b.port").len(), 1);
+        assert!(options.get_path_all::<u16>("clients.*.port").is_empty());
+    }
+
+    #[test]
+    fn get_path_keyed() {
+        use super::PathKey;
+
+        const C: PathKey<i32> = PathKey::new("a.b.c");
+        const D: PathKey<i32> = PathKey::new("a.d");
+
+        let options = setup_nested();
+        assert_eq!(options.get_path_keyed(C), Some(42));
+        assert_eq!(options.get_path_keyed(D), None);
+        assert_eq!(C.path(), "a.b.c");
+    }
+
+    #[test]
+    fn has_path() {
+        let options = setup_nested();
+        assert!(options.has_path("a.b.c"));
+        assert!(options.has_path("a.b"));
+        assert!(options.has_path("a"));
+        assert!(!options.has_path("a.x.c"));
+        assert!(!options.has_path("a.d.c"));
+        assert!(!options.has_path("z"));
+    }
+
+    #[test]
+    fn path_cow() {
+        use std::borrow::Cow;
+
+        let mut options = setup_nested();
+        options
+            .path_entry_or_insert::<Options>("a.b")
+            .set("d", "Hello, world!".to_string())
+            .set("e", "69");
+        let value = options.path_cow::<String>("a.b.d").unwrap();
+        assert!(matches!(value, Cow::Borrowed(_)));
+        assert!(std::ptr::eq(
+            &*value,
+            options.get_path_ref::<String>("a.b.d").unwrap(),
+        ));
+        let value = options.path_cow::<i32>("a.b.e").unwrap();
+        assert!(matches!(value, Cow::Owned(69)));
+        assert!(options.path_cow::<i32>("a.d").is_none());
+        assert!(options.path_cow::<i32>("a.z").is_none());
+    }
+
+    #[test]
+    fn path_str() {
+        use std::borrow::Cow;
+
+        let mut options = setup_nested();
+        options
+            .path_entry_or_insert::<Options>("a.b")
+            .set("d", "static")
+            .set("e", "owned".to_string())
+            .set("f", Cow::<'static, str>::Borrowed("cow"));
+        assert_eq!(options.path_str("a.b.d"), Some("static"));
+        assert_eq!(options.path_str("a.b.e"), Some("owned"));
+        assert_eq!(options.path_str("a.b.f"), Some("cow"));
+        assert_eq!(options.path_str("a.b.c"), None);
+        assert_eq!(options.path_str("a.z.d"), None);
+    }
+
+    #[test]
+    fn path_flag() {
+        let mut options = setup_nested();
+        options
+            .path_entry_or_insert::<Options>("features")
+            .set("fast_mode", true)
+            .set("safe_mode", false)
+            .set("verbose", 1);
+        assert!(options.path_flag("features.fast_mode"));
+        assert!(!options.path_flag("features.safe_mode"));
+        assert!(!options.path_flag("features.slow_mode"));
+        assert!(!options.path_flag("features.verbose"));
+        assert!(options.path_flag("a.d"));
+    }
+
+    #[test]
+    fn path_or_env() {
+        let mut options = setup_nested();
+        options
+            .path_entry_or_insert::<Options>("db")
+            .set("host", "localhost");
+        assert_eq!(
+            options.path_or_env("db.host", "CARGO_PKG_NAME"),
+            Some("localhost".to_string()),
+        );
+        assert_eq!(
+            options.path_or_env("db.user", "CARGO_PKG_NAME"),
+            Some(env!("CARGO_PKG_NAME").to_string()),
+        );
+        assert_eq!(
+            options.path_or_env("x.y", "CARGO_PKG_NAME"),
+            Some(env!("CARGO_PKG_NAME").to_string()),
+        );
+        assert_eq!(
+            options.path_or_env("x.y", "OPTIONS_PATH_OR_ENV_UNSET"),
+            None
+        );
+    }
+
+    #[test]
+    fn path_any() {
+        let options = setup_nested();
+        let value = options.path_any("a.b.c").unwrap();
+        assert!(value.downcast_ref::<bool>().is_none());
+        assert_eq!(value.downcast_ref::<i32>(), Some(&42));
+        assert!(options.path_any("a.b").unwrap().is::<Options>());
+        assert!(options.path_any("a.d.e").is_none());
+        assert!(options.path_any("a.z").is_none());
+    }
+
+    #[test]
+    fn path_list() {
+        let mut options = setup_nested();
+        let server = options.path_entry_or_insert::<Options>("server");
+        server
+            .set("hosts", "a, b,c")
+            .set("ports", vec!["80".to_string()]);
+        assert_eq!(
+            options.path_list("server.hosts", ',').unwrap(),
+            &["a", "b", "c"]
+        );
+        assert_eq!(options.path_list("server.ports", ',').unwrap(), &["80"]);
+        assert_eq!(options.path_list("a.b.c", ','), None);
+        assert_eq!(options.path_list("server.z", ','), None);
+    }
+
+    #[test]
+    fn path_display() {
+        let mut options = setup_nested();
+        options.set("e", vec![4u8, 2u8]).set("f", ());
+        assert_eq!(options.path_display("a.b.c").unwrap(), "42");
+        assert_eq!(options.path_display("a.d").unwrap(), "true");
+        assert_eq!(options.path_display("e").unwrap(), "[4, 2]");
+        assert_eq!(options.path_display("f"), None);
+        assert_eq!(options.path_display("a.b.z"), None);
+    }
+
+    #[test]
+    fn subtree_values() {
+        let mut limits = Options::new();
+        limits
+            .set("connections", 100u32)
+            .set("requests", 1000u32)
+            .set("strict", true);
+        let mut options = setup_nested();
+        options
+            .path_entry_or_insert::<Options>("x")
+            .set("limits", limits);
+        let mut values = options.subtree_values::<u32>("x.limits");
+        values.sort();
+        assert_eq!(
+            values,
+            &[
+                ("connections".to_string(), 100),
+                ("requests".to_string(), 1000)
+            ],
+        );
+        assert!(options.subtree_values::<u32>("a.b.c").is_empty());
+        assert!(options.subtree_values::<u32>("z").is_empty());
+    }
+
+    #[test]
+    fn subtree_map() {
+        let mut options = setup_nested();
+        options
+            .path_entry_or_insert::<Options>("x.weights")
+            .set("a", 0.25)
+            .set("b", 0.75)
+            .set("c", "heavy");
+        let weights = options.subtree_map::<f64>("x.weights").unwrap();
+        assert_eq!(weights.len(), 2);
+        assert_eq!(weights["a"], 0.25);
+        assert_eq!(weights["b"], 0.75);
+        assert!(options.subtree_map::<f64>("a").unwrap().is_empty());
+        assert!(options.subtree_map::<f64>("a.b.c").is_none());
+    }
+
+    #[test]
+    fn path_children() {
+        let mut alpha = Options::new();
+        alpha.set("port", 80);
+        let mut beta = Options::new();
+        beta.set("port", 81);
+        let mut servers = Options::new();
+        servers.set("alpha", alpha).set("beta", beta);
+        let mut options = setup_nested();
+        options.set("servers", servers);
+        let mut names = options
+            .path_children("servers")
+            .unwrap()
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, &["alpha", "beta"]);
+        let mut names = options.path_children("a").unwrap().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, &["b", "d"]);
+        assert_eq!(
+            options.path_children("a.b").unwrap().collect::<Vec<_>>(),
+            &["c"]
+        );
+        assert!(options.path_children("a.b.c").is_none());
+        assert!(options.path_children("z").is_none());
+    }
+
+    #[test]
+    fn get_path_or() {
+        let options = setup_nested();
+        assert_eq!(options.get_path_or("a.b.c", 24), 42);
+        assert_eq!(options.get_path_or("a.b.z", 24), 24);
+        assert!(!options.get_path_or("a.b.c", false));
+    }
+
+    #[test]
+    fn get_trait_ref() {
+        trait Shape {
+            fn area(&self) -> f64;
+        }
+
+        struct Square(f64);
+        struct Circle(f64);
+
+        impl Shape for Square {
+            fn area(&self) -> f64 {
+                self.0 * self.0
+            }
+        }
+
+        impl Shape for Circle {
+            fn area(&self) -> f64 {
+                3.0 * self.0 * self.0
+            }
+        }
+
+        let mut options = setup();
+        options.register_trait::<Square, dyn Shape>(|value| value);
+        options.register_trait::<Circle, dyn Shape>(|value| value);
+        options
+            .set("square", Square(2.0))
+            .set("circle", Circle(1.0));
+        let area = |name| options.get_trait_ref::<dyn Shape>(name).map(Shape::area);
+        assert_eq!(area("square"), Some(4.0));
+        assert_eq!(area("circle"), Some(3.0));
+        assert_eq!(area("a"), None);
+        assert_eq!(area("z"), None);
+    }
+
+    #[test]
+    fn path_entry_or_insert() {
+        let mut options = setup_nested();
+        *options.path_entry_or_insert::<i32>("a.x.y.z") += 1;
+        *options.path_entry_or_insert::<i32>("a.x.y.z") += 1;
+        assert_eq!(options.get_path::<i32>("a.x.y.z"), Some(2));
+        assert!(options.get_path_ref::<Options>("a.x.y").is_some());
+        assert_eq!(options.get_path::<bool>("a.d"), Some(true));
+        options.path_entry_or_insert::<Vec<u8>>("a.d.e").push(1);
+        assert_eq!(options.get_path::<Vec<u8>>("a.d.e"), Some(vec![1]));
+        *options.path_entry_or_insert::<u8>("a.b.c") = 1;
+        assert_eq!(options.get_path::<u8>("a.b.c"), Some(1));
+    }
+
+    #[test]
+    fn path_or_insert_with() {
+        let mut options = setup_nested();
+        let mut calls = 0;
+        let mut initialize = || {
+            calls += 1;
+            "localhost".to_string()
+        };
+        assert_eq!(
+            options.path_or_insert_with("a.db.host", &mut initialize),
+            "localhost"
+        );
+        assert_eq!(
+            options.path_or_insert_with("a.db.host", &mut initialize),
+            "localhost"
+        );
+        assert_eq!(calls, 1);
+        assert_eq!(
+            options.get_path::<String>("a.db.host").unwrap(),
+            "localhost"
+        );
+        assert_eq!(options.path_or_insert_with("a.b.c", || 0), 42);
+    }
+
+    #[test]
+    fn path_entry() {
+        let mut options = setup_nested();
+        *options.path_entry("a.x.y").or_insert(0) += 1;
+        assert_eq!(options.get_path::<i32>("a.x.y"), Some(1));
+        assert_eq!(options.get_path::<i32>("a.b.c"), Some(42));
+
+        options
+            .path_entry("z.y")
+            .and_modify(|value: &mut i32| *value += 1);
+        assert!(!options.has("z"));
+
+        let value = options
+            .path_entry("a.b.c")
+            .and_modify(|value: &mut i32| *value += 1)
+            .or_insert_with::<i32, _>(|| unreachable!());
+        assert_eq!(*value, 43);
+    }
+
+    #[test]
+    fn take_path() {
+        let mut options = setup_nested();
+        options.path_entry_or_insert::<Options>("x.y").set("z", 'z');
+        assert_eq!(options.take_path::<bool>("a.b.c"), None);
+        assert_eq!(options.take_path::<i32>("a.b.c"), Some(42));
+        assert!(!options.has_path("a.b"));
+        assert!(options.has_path("a.d"));
+        assert_eq!(options.take_path::<char>("x.y.z"), Some('z'));
+        assert!(!options.has("x"));
+        assert_eq!(options.take_path::<bool>("a.d"), Some(true));
+        assert!(!options.has("a"));
+        assert_eq!(options.take_path::<bool>("a.d"), None);
+    }
+
+    #[test]
+    fn get_tuple() {
+        let options = setup();
+        assert_eq!(options.get_tuple2::<i32, bool>("a", "b"), Some((42, true)));
+        assert_eq!(
+            options.get_tuple3::<i32, bool, &str>("a", "b", "c"),
+            Some((42, true, "Hi, there!")),
+        );
+        assert_eq!(
+            options.get_tuple4::<i32, bool, &str, Vec<u8>>("a", "b", "c", "e"),
+            Some((42, true, "Hi, there!", vec![4u8, 2u8])),
+        );
+        assert_eq!(options.get_tuple2::<i32, bool>("a", "z"), None);
+        assert_eq!(options.get_tuple2::<i32, i32>("a", "b"), None);
+    }
+
+    #[test]
+    fn get_ref() {
+        let options = setup();
+
+        macro_rules! test(
+            ($name:expr, $value:expr, $kind:ty) => (
+                assert_eq!(options.get_ref::<$kind>($name).unwrap(), $value)
+            );
+        );
+
+        test!("a", &42, i32);
+        test!("b", &true, bool);
+        test!("c", &"Hi, there!", &str);
+        test!("d", "Hello, world!", String);
+        test!("e", &vec![4u8, 2u8], Vec<u8>);
+    }
+
+    #[test]
+    fn get_ref_or_else() {
+        static DEFAULT: String = String::new();
+
+        let options = setup();
+        let value = options.get_ref_or_else::<String, _>("d", || unreachable!());
+        assert_eq!(value, "Hello, world!");
+        assert!(options.get_ref_or_else("a", || &DEFAULT).is_empty());
+        assert!(options.get_ref_or_else("z", || &DEFAULT).is_empty());
+    }
+
+    #[test]
+    fn try_get_ref() {
+        use super::GetError;
+
+        let options = setup();
+        assert_eq!(options.try_get_ref::<Vec<u8>>("e"), Ok(&vec![4, 2]));
+        assert_eq!(options.try_get_ref::<i32>("z"), Err(GetError::Missing));
+        let error = options.try_get_ref::<bool>("a").unwrap_err();
+        assert_eq!(
+            error,
+            GetError::Mismatch {
+                expected: "bool",
+                actual: "i32",
+            },
+        );
+        assert_eq!(error.to_string(), "the parameter is of type i32, not bool");
+    }
+
+    #[test]
+    fn get_converted() {
+        use std::num::NonZeroU32;
+
+        use super::{ConvertError, GetError};
+
+        let mut options = setup();
+        options.set("f", 0u32).set("g", 8u32);
+        let convert = |value: u32| NonZeroU32::new(value).ok_or("zero");
+        assert_eq!(
+            options.get_converted("g", convert),
+            Ok(NonZeroU32::new(8).unwrap()),
+        );
+        assert_eq!(
+            options.get_converted("f", convert),
+            Err(ConvertError::Convert("zero")),
+        );
+        assert_eq!(
+            options.get_converted("z", convert),
+            Err(ConvertError::Get(GetError::Missing)),
+        );
+        let error = options.get_converted("a", convert).unwrap_err();
+        assert_eq!(
+            error,
+            ConvertError::Get(GetError::Mismatch {
+                expected: "u32",
+                actual: "i32",
+            }),
+        );
+        assert_eq!(error.to_string(), "the parameter is of type i32, not u32");
+        assert_eq!(
+            ConvertError::<&str>::Convert("zero").to_string(),
+            "the conversion failed: zero",
+        );
+    }
+
+    #[test]
+    fn get_any_mut() {
+        let mut options = setup();
+        assert!(options.get_any("a").unwrap().is::<i32>());
+        let value = options.get_any_mut("a").unwrap();
+        assert!(value.downcast_mut::<bool>().is_none());
+        *value.downcast_mut::<i32>().unwrap() = 24;
+        assert_eq!(options.get::<i32>("a"), Some(24));
+        assert!(options.get_any_mut("z").is_none());
+    }
+
+    #[test]
+    fn clone_value() {
+        let options = setup();
+        let mut other = Options::new();
+        other.set_value("d", options.clone_value("d").unwrap());
+        assert_eq!(
+            options.get::<String>("d"),
+            Some("Hello, world!".to_string())
+        );
+        assert_eq!(other.get::<String>("d"), Some("Hello, world!".to_string()));
+        assert!(options.clone_value("z").is_none());
+
+        let mut options = Options::new();
+        options.set("a", std::cell::Cell::new(42));
+        assert!(options.clone_value("a").is_none());
+    }
+
+    #[test]
+    fn get_or_insert_ref() {
+        let mut options = setup();
+        assert_eq!(options.get_or_insert_ref("a", 24), &42);
+        assert_eq!(options.get_or_insert_ref("z", 24), &24);
+        assert_eq!(options.get_or_insert_ref("b", 24), &24);
+        assert_eq!(options.get::<i32>("z"), Some(24));
+        assert_eq!(options.get::<i32>("b"), Some(24));
+    }
+
+    #[test]
+    fn get_or_factory() {
+        use std::cell::Cell;
+
+        thread_local! {
+            static CALLS: Cell<usize> = const { Cell::new(0) };
+        }
+        fn factory() -> i32 {
+            CALLS.with(|calls| calls.set(calls.get() + 1));
+            24
+        }
+
+        let mut options = setup();
+        options.register_default::<i32>(factory);
+        assert_eq!(options.get_or_factory::<i32>("a"), Some(42));
+        assert_eq!(CALLS.with(Cell::get), 0);
+        assert_eq!(options.get_or_factory::<i32>("z"), Some(24));
+        assert_eq!(options.get_or_factory::<i32>("z"), Some(24));
+        assert_eq!(CALLS.with(Cell::get), 1);
+        assert_eq!(options.get_or_factory::<i32>("b"), Some(24));
+        assert_eq!(CALLS.with(Cell::get), 2);
+        assert_eq!(options.get::<i32>("b"), Some(24));
+
+        options.protect(&["y"]);
+        assert_eq!(options.get_or_factory::<i32>("y"), Some(24));
+        assert_eq!(options.get_or_factory::<i32>("y"), Some(24));
+        assert_eq!(CALLS.with(Cell::get), 4);
+        assert!(!options.has("y"));
+
+        assert_eq!(options.get_or_factory::<bool>("x"), None);
+        assert!(!options.has("x"));
+    }
+
+    #[test]
+    fn get_mut() {
+        let mut options = setup();
+
+        macro_rules! test(
+            ($name:expr, $value:expr, $kind:ty) => ({
+                *options.get_mut::<$kind>($name).unwrap() = $value;
+                assert_eq!(options.get::<$kind>($name).unwrap(), $value);
+            });
+        );
+
+        test!("a", 24, i32);
+        test!("b", false, bool);
+        test!("c", "Hi, here!", &str);
+        test!("d", "Bye, world!".to_string(), String);
+        test!("e", vec![2u8, 4u8], Vec<u8>);
+    }
+
+    #[test]
+    fn shrink_to() {
+        let mut options = Options::new();
+        for i in 0..1000 {
+            options.set(i.to_string(), i);
+        }
+        options.retain_reporting(|name, _| name.len() == 1);
+        let capacity = options.stats().capacity;
+        options.shrink_to(100);
+        assert!(options.stats().capacity < capacity);
+        assert!(options.stats().capacity >= 100);
+        options.shrink_to(0);
+        assert!(options.stats().capacity >= 10);
+    }
+
+    #[test]
+    fn stats() {
+        let stats = Options::new().stats();
+        assert_eq!((stats.len, stats.capacity, stats.distinct_types), (0, 0, 0));
+        assert_eq!(stats.load_factor, 0.0);
+
+        let mut options = setup();
+        options.set("f", 69);
+        let stats = options.stats();
+        assert_eq!(stats.len, 6);
+        assert!(stats.capacity >= stats.len);
+        assert_eq!(stats.load_factor, 6.0 / stats.capacity as f64);
+        assert_eq!(stats.distinct_types, 5);
+    }
+
+    #[test]
+    fn group_by_type() {
+        let mut options = setup();
+        options.set("f", 69).set("g", "Bye!".to_string());
+        let groups = options.group_by_type();
+        assert_eq!(groups.len(), 5);
+        assert_eq!(groups["i32"], &["a", "f"]);
+        assert_eq!(groups["bool"], &["b"]);
+        assert_eq!(groups["&str"], &["c"]);
+        assert_eq!(groups[std::any::type_name::<String>()], &["d", "g"]);
+    }
+
+    #[test]
+    fn display_map() {
+        let mut options = setup();
+        options.set("f", 1.5f64).set("g", ());
+        let map = options.display_map();
+        assert_eq!(
+            map.into_iter().collect::<Vec<_>>(),
+            &[
+                ("a", "42".to_string()),
+                ("b", "true".to_string()),
+                ("c", "Hi, there!".to_string()),
+                ("d", "Hello, world!".to_string()),
+                ("e", "[4, 2]".to_string()),
+                ("f", "1.5".to_string()),
+            ],
+        );
+    }
+
+    #[test]
+    fn set_secret() {
+        let mut options = setup_nested();
+        options
+            .path_entry_or_insert::<Options>("db")
+            .set("password", "hunter2")
+            .set("user", "admin");
+        options
+            .set("token", "abc")
+            .set_secret(&["token", "db.password"]);
+        assert_eq!(options.display_map()["token"], "***");
+        assert_eq!(options.get::<&str>("token"), Some("abc"));
+        let variables = options.to_env_vars("");
+        assert_eq!(variables["DB_PASSWORD"], "***");
+        assert_eq!(variables["DB_USER"], "admin");
+        #[cfg(feature = "json")]
+        assert_eq!(
+            options.to_json().to_string(),
+            r#"{"a":{"b":{"c":42},"d":true},"db":{"password":"***","user":"admin"},"token":"***"}"#,
+        );
+        assert_eq!(options.get_path::<&str>("db.password"), Some("hunter2"));
+
+        let mut options = Options::with_key_normalizer(str::to_lowercase);
+        options.set("Password", "x").set("User", "y");
+        options.set_secret(&["PASSWORD"]);
+        assert_eq!(options.display_map()["password"], "***");
+        assert_eq!(options.display_map()["user"], "y");
+    }
+
+    #[test]
+    fn to_sorted_pairs() {
+        let mut options = Options::new();
+        options
+            .set("b", 2)
+            .set("c", "three")
+            .set("a", 'x')
+            .set("d", ());
+        assert_eq!(
+            options.to_sorted_pairs(),
+            &[
+                ("a".to_string(), "x".to_string()),
+                ("b".to_string(), "2".to_string()),
+                ("c".to_string(), "three".to_string()),
+            ],
+        );
+    }
+
+    #[test]
+    fn write_properties() {
+        let mut options = setup_nested();
+        options
+            .set("b", "x=1\ny=2")
+            .set("#c", " padded ")
+            .set("d", 'é')
+            .set("e", ());
+        let mut buffer = Vec::new();
+        options.write_properties(&mut buffer).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "\\#c=\\ padded\\ \nb=x\\=1\\ny\\=2\nd=é\n",
+        );
+        let mut buffer = Vec::new();
+        options.flatten().write_properties(&mut buffer).unwrap();
+        assert!(String::from_utf8(buffer)
+            .unwrap()
+            .contains("a.b.c=42\na.d=true\n"));
+    }
+
+    #[test]
+    fn to_env_vars() {
+        let mut options = setup_nested();
+        options.set("max-size", 10).set("e", ());
+        let mut variables = options.to_env_vars("APP_").into_iter().collect::<Vec<_>>();
+        variables.sort();
+        assert_eq!(
+            variables,
+            &[
+                ("APP_A_B_C".to_string(), "42".to_string()),
+                ("APP_A_D".to_string(), "true".to_string()),
+                ("APP_MAX_SIZE".to_string(), "10".to_string()),
+            ],
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn to_json() {
+        use super::Json;
+
+        let mut options = setup_nested();
+        options
+            .set("e", vec![1u64, u64::MAX])
+            .set("f", 'x')
+            .set("g", 1.5f32)
+            .set("h", Duration::from_secs(1));
+        assert_eq!(
+            options.to_json(),
+            Json::Object(
+                [
+                    (
+                        "a".to_string(),
+                        Json::Object(
+                            [
+                                (
+                                    "b".to_string(),
+                                    Json::Object([("c".to_string(), Json::Integer(42))].into()),
+                                ),
+                                ("d".to_string(), Json::Bool(true)),
+                            ]
+                            .into(),
+                        ),
+                    ),
+                    (
+                        "e".to_string(),
+                        Json::Array(vec![Json::Integer(1), Json::Float(u64::MAX as f64)]),
+                    ),
+                    ("f".to_string(), Json::String("x".to_string())),
+                    ("g".to_string(), Json::Float(1.5)),
+                    ("h".to_string(), Json::Null),
+                ]
+                .into(),
+            ),
+        );
+        assert_eq!(
+            options.to_json().to_string(),
+            r#"{"a":{"b":{"c":42},"d":true},"e":[1,1.8446744073709552e19],"f":"x","g":1.5,"h":null}"#,
+        );
+    }
+
+    #[test]
+    fn ci_collisions() {
+        let mut options = setup();
+        assert!(options.ci_collisions().is_empty());
+        options.set("Host", "a").set("host", "b").set("port", 80);
+        assert_eq!(options.ci_collisions(), &[&["Host", "host"]]);
+        options.set("A", 1).set("HOST", "c");
+        assert_eq!(
+            options.ci_collisions(),
+            &[&["A", "a"][..], &["HOST", "Host", "host"][..]],
+        );
+    }
+
+    #[test]
+    fn filter() {
+        let mut options = setup();
+        options.set("aa", 24).set("ab", true);
+        let mut names = options
+            .filter(|name, value| name.starts_with('a') && value.get_ref::<i32>().is_some())
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, &["a", "aa"]);
+    }
+
+    #[test]
+    fn with_hasher() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::BuildHasherDefault;
+
+        let mut options = Options::with_hasher(BuildHasherDefault::<DefaultHasher>::default());
+        options.set("a", 42).set("b", true);
+        assert_eq!(options.get::<i32>("a"), Some(42));
+        assert_eq!(options.get::<bool>("b"), Some(true));
+        assert_eq!(options.names().count(), 2);
+
+        let mut options = Options::<BuildHasherDefault<DefaultHasher>>::default();
+        options.set("a", 42);
+        assert_eq!(options.get::<i32>("a"), Some(42));
+    }
+
+    #[test]
+    fn get_at() {
+        let mut options = Options::new();
+        options.set("c", 1).set("a", 2).set("b", 3);
+        let (name, value) = options.get_at(1).unwrap();
+        assert_eq!(name, "a");
+        assert_eq!(value.get::<i32>(), Some(2));
+        options.set("a", 4);
+        assert_eq!(options.get_at(1).unwrap().0, "b");
+        assert_eq!(options.get_at(2).unwrap().0, "a");
+        assert!(options.get_at(3).is_none());
+
+        let pairs = ["c", "a", "b"].map(|name| (name.to_string(), String::new()));
+        let options = Options::from_str_pairs(pairs);
+        let names = (0..3).map(|i| options.get_at(i).unwrap().0.clone());
+        assert_eq!(names.collect::<Vec<_>>(), ["c", "a", "b"]);
+    }
+
+    #[test]
+    fn iter_typed() {
+        use super::TypedRef;
+
+        let mut options = setup();
+        options.set("f", 1.5f32).set("g", u64::MAX);
+        let mut values = options.iter_typed().collect::<Vec<_>>();
+        values.sort_by(|one, other| one.0.cmp(other.0));
+        let values = values
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            &[
+                TypedRef::Int(42),
+                TypedRef::Bool(true),
+                TypedRef::Str("Hi, there!"),
+                TypedRef::Str("Hello, world!"),
+                TypedRef::Other(std::any::type_name::<Vec<u8>>()),
+                TypedRef::Float(1.5),
+                TypedRef::Other("u64"),
+            ],
+        );
+    }
+
+    #[test]
+    fn iter_mut() {
+        let mut options = setup();
+        for (_, value) in &mut options {
+            value.set(69);
+        }
+
+        macro_rules! test(
+            ($name:expr) => (assert_eq!(options.get_ref::<i32>($name).unwrap(), &69));
+        );
+
+        test!("a");
+        test!("b");
+        test!("c");
+        test!("d");
+        test!("e");
+    }
+
+    #[test]
+    fn for_each_mut() {
+        let mut options = setup();
+        options.for_each_mut(|_, value| value.set(69));
+        for name in ["a", "b", "c", "d", "e"] {
+            assert_eq!(options.get::<i32>(name), Some(69));
+        }
+    }
+
+    #[test]
+    fn replace() {
+        let mut options = setup();
+        let mut old = Vec::new();
+        for (name, value) in &mut options {
+            old.push((name.clone(), value.replace(69)));
+        }
+        old.sort_by(|one, other| one.0.cmp(&other.0));
+
+        assert_eq!(old[0].1.downcast_ref::<i32>(), Some(&42));
+        assert_eq!(old[1].1.downcast_ref::<bool>(), Some(&true));
+        assert_eq!(old[2].1.downcast_ref::<&str>(), Some(&"Hi, there!"));
+        assert_eq!(old[3].1.downcast_ref::<String>().unwrap(), "Hello, world!");
+        assert_eq!(old[4].1.downcast_ref::<Vec<u8>>(), Some(&vec![4u8, 2u8]));
+        for name in ["a", "b", "c", "d", "e"] {
+            assert_eq!(options.get::<i32>(name), Some(69));
+        }
+    }
+
+    #[test]
+    fn extract() {
+        let mut options = setup();
+        options.set("f", 24);
+        let other = options.extract(|_, value| value.get_ref::<i32>().is_some());
+        let mut names = other.names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, &["a", "f"]);
+        let mut names = options.names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, &["b", "c", "d", "e"]);
+    }
+
+    #[test]
+    fn partition() {
+        let mut options = setup();
+        options.set("db.host", "localhost").set("db.port", 5432);
+        let (one, other) = options.partition(|name, _| name.starts_with("db."));
+        let mut names = one.names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, &["db.host", "db.port"]);
+        let mut names = other.names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, &["a", "b", "c", "d", "e"]);
+    }
+
+    #[test]
+    fn into_typed_map() {
+        let mut options = setup();
+        options.set("f", 24);
+        let (typed, other) = options.into_typed_map::<i32>();
+        assert_eq!(typed.len(), 2);
+        assert_eq!(typed["a"], 42);
+        assert_eq!(typed["f"], 24);
+        let mut names = other.names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, &["b", "c", "d", "e"]);
+        assert_eq!(other.get::<bool>("b"), Some(true));
+    }
+
+    #[test]
+    fn try_clone() {
+        use std::rc::Rc;
+
+        let mut options = setup_nested();
+        options
+            .set("e", vec!["x".to_string()])
+            .set("f", Duration::from_secs(1))
+            .set_shared("g", Rc::new(Duration::from_secs(1)))
+            .set_shared("h", Rc::new(Duration::from_secs(1)));
+        options.get_path_mut::<Options>("a").unwrap().set("f", ());
+        for (name, value) in &options {
+            let clone = value.try_clone();
+            assert_eq!(clone.is_some(), name != "f");
+            if let Some(clone) = clone {
+                assert_eq!(clone.type_id(), value.type_id());
+            }
+        }
+        let clone = options.remove_value("a").unwrap().try_clone().unwrap();
+        let clone = clone.get_ref::<Options>().unwrap();
+        assert_eq!(clone.get_path::<i32>("b.c"), Some(42));
+        assert!(!clone.has("f"));
+        let g = options.remove_value("g").unwrap();
+        options.set_value("i", g.try_clone().unwrap());
+        options.set_value("g", g);
+        assert!(options.same_value("g", "i"));
+        assert!(!options.same_value("g", "h"));
+    }
+
+    #[test]
+    fn into_shared() {
+        use super::FrozenOptions;
+        use std::rc::Rc;
+        use std::sync::Arc;
+
+        let mut options = setup_nested();
+        options
+            .set("b", "b")
+            .set("c", Rc::new(42))
+            .set_shared("d", Rc::new(vec![1u8]));
+        let options = options.into_shared();
+        assert!(options.get_path_ref::<FrozenOptions>("a.b").is_some());
+        assert!(!options.has("c"));
+        assert_eq!(options.get::<Vec<u8>>("d"), Some(vec![1]));
+
+        let mut other = setup_nested();
+        let captured = capture(&mut other);
+        other
+            .path_entry_or_insert::<Options>("a")
+            .set("x", Rc::new(1));
+        other.into_shared();
+        assert_eq!(
+            captured(),
+            &["the parameter \"x\" is of type alloc::rc::Rc<i32>, which cannot be shared"],
+        );
+        let threads = (0..4)
+            .map(|_| {
+                let options = Arc::clone(&options);
+                std::thread::spawn(move || {
+                    (options.get_path::<i32>("a.b.c"), options.get::<&str>("b"))
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            assert_eq!(thread.join().unwrap(), (Some(42), Some("b")));
+        }
+        assert_eq!(options.names().count(), 3);
+    }
+
+    #[test]
+    fn flatten() {
+        let mut options = setup_nested();
+        options
+            .set("e", "Hi, there!".to_string())
+            .set("f", Options::new())
+            .set("g", Duration::from_secs(1));
+        let options = options.flatten();
+        let mut names = options.names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, &["a.b.c", "a.d", "e"]);
+        assert_eq!(options.get::<i32>("a.b.c"), Some(42));
+        assert_eq!(options.get::<bool>("a.d"), Some(true));
+        assert_eq!(options.get::<String>("e").unwrap(), "Hi, there!");
+
+        let mut options = setup_nested();
+        options.path_entry_or_insert::<Options>("a").set("x.y", 1);
+        let flat = options.flatten();
+        assert_eq!(flat.get::<i32>(r"a.x\.y"), Some(1));
+        for (path, _) in options.walk_leaves() {
+            assert!(options.has_path(&path) && flat.has(&path));
+        }
+        assert_eq!(options.get_path::<i32>(r"a.x\.y"), Some(1));
+    }
+
+    #[test]
+    fn walk_leaves() {
+        let mut options = setup_nested();
+        options.set("e", ()).set("f", Options::new());
+        options
+            .get_path_mut::<Options>("a.b")
+            .unwrap()
+            .set("g", 'x');
+        let leaves = options.walk_leaves();
+        let paths = leaves.iter().map(|(path, _)| path).collect::<Vec<_>>();
+        assert_eq!(paths, &["a.b.c", "a.b.g", "a.d", "e"]);
+        assert_eq!(leaves[0].1.get::<i32>(), Some(42));
+        assert_eq!(leaves[1].1.get::<char>(), Some('x'));
+    }
+
+    #[test]
+    fn walk_typed() {
+        let mut options = setup_nested();
+        options.set("e", "x".to_string()).set("f", "y");
+        options
+            .get_path_mut::<Options>("a.b")
+            .unwrap()
+            .set("g", "z".to_string());
+        assert_eq!(
+            options.walk_typed::<String>(),
+            [
+                ("a.b.g".to_string(), "z".to_string()),
+                ("e".to_string(), "x".to_string()),
+            ],
+        );
+        assert_eq!(options.walk_typed::<i32>(), [("a.b.c".to_string(), 42)]);
+        assert!(options.walk_typed::<char>().is_empty());
+    }
+
+    #[test]
+    fn leaf_count() {
+        let mut options = setup_nested();
+        options.set("e", ()).set("f", Options::new());
+        assert_eq!(options.leaf_count(), 3);
+        assert_eq!(options.leaf_count(), options.walk_leaves().len());
+        assert_eq!(Options::new().leaf_count(), 0);
+    }
+
+    #[test]
+    fn unflatten() {
+        let mut options = Options::new();
+        options
+            .set("db.host", "localhost")
+            .set("db.port", 5432)
+            .set("db.pool.size", 4)
+            .set("db.pool", 8)
+            .set("debug", true);
+        let options = options.unflatten('.');
+        let mut names = options.names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, &["db", "debug"]);
+        assert_eq!(options.get_path::<&str>("db.host"), Some("localhost"));
+        assert_eq!(options.get_path::<i32>("db.port"), Some(5432));
+        assert_eq!(options.get_path::<i32>("db.pool.size"), Some(4));
+        assert_eq!(options.get::<bool>("debug"), Some(true));
+        assert_eq!(options.flatten().names().count(), 4);
+
+        let mut options = setup_nested();
+        options
+            .path_entry_or_insert::<Options>("a")
+            .set(r"x.y\z", 1);
+        let options = options.flatten().unflatten('.');
+        assert_eq!(options.get_path::<i32>(r"a.x\.y\\z"), Some(1));
+        assert_eq!(options.get_path::<i32>("a.b.c"), Some(42));
+        assert_eq!(options.path_children("a").unwrap().count(), 3);
+    }
+
+    #[test]
+    fn namespace() {
+        let mut options = Options::new();
+        options.set("db.host", "localhost").set("debug", true);
+        options.namespace_mut("db.").set("port", 5432u16);
+        assert_eq!(options.namespace_mut("db.").get::<u16>("port"), Some(5432));
+        let namespace = options.namespace("db.");
+        assert_eq!(namespace.get::<u16>("port"), Some(5432));
+        assert_eq!(namespace.get_ref::<&str>("host"), Some(&"localhost"));
+        assert!(namespace.has("host") && !namespace.has("debug"));
+        let mut names = namespace.names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, &["host", "port"]);
+        assert_eq!(options.get::<u16>("db.port"), Some(5432));
+    }
+
+    #[test]
+    fn overlay() {
+        let base = setup();
+        let mut top = Options::new();
+        top.set("a", "shadowed").set("f", 69);
+        let overlay = base.overlay(&top);
+        assert_eq!(overlay.get::<&str>("a"), Some("shadowed"));
+        assert_eq!(overlay.get::<i32>("a"), None);
+        assert_eq!(overlay.get::<bool>("b"), Some(true));
+        assert_eq!(overlay.get::<i32>("f"), Some(69));
+        assert!(overlay.has("f") && overlay.has("e") && !overlay.has("z"));
+        let mut names = overlay.names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, &["a", "b", "c", "d", "e", "f"]);
+        assert_eq!(base.get::<i32>("a"), Some(42));
+    }
+
+    #[test]
+    fn scoped() {
+        let mut options = setup();
+        {
+            let mut scoped = options.scoped();
+            scoped.set("a", 24).set("a", 12).set("z", "Bye!");
+            assert!(scoped.remove("b"));
+            assert!(!scoped.remove("y"));
+            assert_eq!(scoped.get::<i32>("a"), Some(12));
+            assert_eq!(scoped.get::<&str>("z"), Some("Bye!"));
+            assert!(!scoped.has("b"));
+        }
+        assert_eq!(options.get::<i32>("a"), Some(42));
+        assert_eq!(options.get::<bool>("b"), Some(true));
+        assert!(!options.has("z"));
+        assert_eq!(options.names().count(), 5);
+        assert_eq!(options.get_at(0).unwrap().0, "a");
+        assert_eq!(options.get_at(1).unwrap().0, "b");
+
+        let mut options = Options::with_key_normalizer(str::to_lowercase);
+        options.set("a", 42);
+        let version = options.version();
+        {
+            let mut scoped = options.scoped();
+            scoped.set("A", 24);
+            assert!(scoped.remove("a"));
+        }
+        assert_eq!(options.get::<i32>("a"), Some(42));
+        assert_eq!(options.changed_since(version), ["a"]);
+    }
+
+    #[test]
+    fn protect() {
+        use super::KeyError;
+
+        let mut options = setup();
+        options.protect(&["a", "z"]);
+        options.set("a", 69).set("b", false).set("z", 1);
+        assert_eq!(options.get::<i32>("a"), Some(42));
+        assert!(!options.get::<bool>("b").unwrap());
+        assert!(!options.has("z"));
+        assert_eq!(
+            options.set_checked("a", 69).err(),
+            Some(KeyError::Protected)
+        );
+        assert!(options.remove_value("a").is_none());
+        options.clear();
+        assert_eq!(options.names().collect::<Vec<_>>(), &["a"]);
+
+        options.set("b", 0).set("c", "c");
+        options.protect(&["b", "p"]);
+        assert_eq!(options.take_all().len(), 1);
+        options.set("c", 0);
+        drop(options.drain());
+        assert!(!options.has("c"));
+        options.rename_all(|name| format!("{name}{name}"));
+        options.retain_typed::<i32, _>(|_, _| false);
+        options.retain_paths(|_| false);
+        options.retain_reporting(|_, _| false);
+        assert_eq!(options.prune_defaults::<i32>(), 0);
+        assert_eq!(options.extract(|_, _| true).names().count(), 0);
+        assert!(options
+            .batch(|options| {
+                options.unprotect(&["b"]);
+                options.set("b", 1);
+                options.protect(&["b"]);
+                Err(())
+            })
+            .is_err());
+        assert_eq!(options.get::<i32>("a"), Some(42));
+        assert_eq!(options.get::<i32>("b"), Some(1));
+
+        let mut target = Options::new();
+        target.set("a", 42).set("c", true);
+        let reconciliation = options.reconcile(&target);
+        assert!(reconciliation.removed.is_empty());
+        assert!(reconciliation.updated.is_empty());
+        assert_eq!(reconciliation.added, ["c"]);
+        assert_eq!(options.get::<i32>("b"), Some(1));
+
+        assert_eq!(
+            options.apply_overrides(["b=1".into(), "p.q=1".into()]),
+            Ok(0)
+        );
+        assert!(!options.has("p"));
+        assert_eq!(*options.path_entry_or_insert::<i32>("p.q"), 0);
+        assert_eq!(*options.path_entry_or_insert::<i32>("p"), 0);
+        assert_eq!(*options.get_or_insert_ref("b", "b"), "b");
+
+        options.unprotect(&["a"]);
+        options.set("a", 69);
+        assert_eq!(options.get::<i32>("a"), Some(69));
+
+        options.set_aliased("x", &["y"], 1);
+        options.protect(&["x"]);
+        assert_eq!(options.set_checked("y", 5).err(), Some(KeyError::Protected));
+        assert_eq!(options.get::<i32>("y"), Some(1));
+    }
+
+    #[test]
+    fn set_shared() {
+        use std::rc::Rc;
+
+        let value = Rc::new(vec![0u8; 1000]);
+        let mut options = Options::new();
+        options
+            .set_shared("a", value.clone())
+            .set_shared("b", value.clone())
+            .set("c", vec![0u8; 1000])
+            .set("d", ())
+            .set("e", ());
+        assert_eq!(options.get_ref::<Vec<u8>>("a"), Some(&*value));
+        assert!(options.same_value("a", "b"));
+        assert!(!options.same_value("a", "c"));
+        assert!(!options.same_value("d", "e"));
+        assert!(!options.same_value("a", "z"));
+
+        options.get_mut::<Vec<u8>>("a").unwrap()[0] = 1;
+        assert!(!options.same_value("a", "b"));
+        assert_eq!(options.get_ref::<Vec<u8>>("b"), Some(&*value));
+        assert_eq!(options.get_ref::<Vec<u8>>("a").unwrap()[0], 1);
+
+        drop(value);
+        let value = options.remove_value("b").unwrap();
+        assert_eq!(
+            value.into_boxed().downcast::<Vec<u8>>().unwrap().len(),
+            1000
+        );
+    }
+
+    #[test]
+    fn dedup_values() {
+        let mut options = setup();
+        options
+            .set("f", vec![0u8; 1000])
+            .set("g", vec![0u8; 1000])
+            .set("h", vec![0u8; 1000])
+            .set("i", vec![1u8; 1000]);
+        assert_eq!(options.dedup_values::<Vec<u8>>(), 2);
+        assert!(options.same_value("f", "g") && options.same_value("g", "h"));
+        assert!(!options.same_value("f", "i") && !options.same_value("f", "e"));
+        assert_eq!(options.get::<Vec<u8>>("e"), Some(vec![4, 2]));
+        assert_eq!(options.get::<Vec<u8>>("h"), Some(vec![0; 1000]));
+        assert_eq!(options.dedup_values::<Vec<u8>>(), 2);
+        assert!(options.same_value("f", "h"));
+
+        let mut options = Options::new();
+        let mut other = Options::new();
+        other.set("a", 42);
+        options.merge_tracked(other, "file");
+        options.set_with_ttl("b", 42, Duration::from_millis(20));
+        let version = options.version();
+        assert_eq!(options.dedup_values::<i32>(), 1);
+        assert_eq!(options.source_of("a"), Some("file"));
+        assert_eq!(options.get_at(0).unwrap().0, "a");
+        let mut names = options.changed_since(version);
+        names.sort();
+        assert_eq!(names, ["a", "b"]);
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(!options.has("b"));
+    }
+
+    #[test]
+    fn set_with_ttl() {
+        let mut options = setup();
+        options
+            .set_with_ttl("token", "secret", Duration::from_millis(20))
+            .set_with_ttl("session", 42, Duration::from_millis(20))
+            .set_with_ttl("timeout", "1s", Duration::from_millis(20))
+            .set_with_ttl("lasting", true, Duration::from_secs(3600));
+        assert_eq!(options.get::<&str>("token"), Some("secret"));
+        assert!(options.has("session"));
+        assert_eq!(
+            options.get_duration("timeout"),
+            Some(Duration::from_secs(1))
+        );
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(options.get::<&str>("token"), None);
+        assert!(!options.has("token"));
+        assert_eq!(options.get_ref::<i32>("session"), None);
+        assert_eq!(options.get_duration("timeout"), None);
+        assert_eq!(options.get_list("timeout", ','), None);
+        assert!(options.try_get_ref::<&str>("timeout").is_err());
+        assert!(options.get_any("timeout").is_none());
+        assert_eq!(options.checked_get::<i32>("session"), None);
+        assert!(!options.display_map().contains_key("timeout"));
+        assert_eq!(options.get_mut::<i32>("session"), None);
+        assert_eq!(options.names().count(), 8);
+        assert_eq!(options.purge_expired(), 2);
+        assert_eq!(options.names().count(), 6);
+        assert_eq!(options.get::<bool>("lasting"), Some(true));
+        options.set_with_ttl("token", "renewed", Duration::ZERO);
+        assert!(!options.has("token"));
+        options.set("token", "permanent");
+        assert!(options.has("token"));
+    }
+
+    #[test]
+    fn set_lazy() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        let count = Rc::new(Cell::new(0));
+        let mut options = Options::new();
+        {
+            let count = count.clone();
+            options.set_lazy("a", move || {
+                count.set(count.get() + 1);
+                42
+            });
+        }
+        assert!(options.has("a"));
+        assert_eq!(count.get(), 0);
+        assert_eq!(options.get::<i32>("a"), Some(42));
+        assert_eq!(options.get_ref::<i32>("a"), Some(&42));
+        *options.get_mut::<i32>("a").unwrap() = 24;
+        assert_eq!(options.get::<i32>("a"), Some(24));
+        assert_eq!(options.get::<bool>("a"), None);
+        assert_eq!(count.get(), 1);
+
+        options.set_lazy("b", || 69);
+        let (typed, _) = options.into_typed_map::<i32>();
+        assert_eq!(typed["a"], 24);
+        assert_eq!(typed["b"], 69);
+    }
+
+    #[test]
+    fn map_type() {
+        let mut options = setup();
+        options.set("f", 69).set_lazy("g", || 24);
+        assert_eq!(options.map_type(|value: i32| value.to_string()), 3);
+        assert_eq!(options.get::<String>("a").unwrap(), "42");
+        assert_eq!(options.get::<String>("f").unwrap(), "69");
+        assert_eq!(options.get::<String>("g").unwrap(), "24");
+        assert_eq!(options.get::<bool>("b"), Some(true));
+        assert_eq!(options.get::<String>("d").unwrap(), "Hello, world!");
+        assert_eq!(options.map_type(|value: i32| value), 0);
+    }
+
+    #[test]
+    fn rename_all() {
+        let mut options = setup();
+        options.rename_all(|name| name.to_uppercase());
+        let mut names = options.names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, &["A", "B", "C", "D", "E"]);
+        assert_eq!(options.get::<i32>("A"), Some(42));
+        assert_eq!(options.get::<String>("D").unwrap(), "Hello, world!");
+    }
+
+    #[test]
+    fn get_or_take() {
+        let mut options = setup();
+        assert_eq!(
+            options.get_or_take::<String>("d", false).unwrap(),
+            "Hello, world!"
+        );
+        assert!(options.has("d"));
+        assert_eq!(
+            options.get_or_take::<String>("d", true).unwrap(),
+            "Hello, world!"
+        );
+        assert!(!options.has("d"));
+        assert_eq!(options.get_or_take::<bool>("a", true), None);
+        assert!(options.has("a"));
+        assert_eq!(options.get_or_take::<i32>("z", true), None);
+    }
+
+    #[test]
+    fn into_sorted_vec() {
+        let mut options = setup();
+        options.set("0", ());
+        let parameters = options.into_sorted_vec();
+        let names = parameters.iter().map(|(name, _)| name).collect::<Vec<_>>();
+        assert_eq!(names, &["0", "a", "b", "c", "d", "e"]);
+        assert_eq!(parameters[1].1.get::<i32>(), Some(42));
+    }
+
+    #[test]
+    fn remove_value() {
+        let mut one = setup();
+        let mut other = Options::new();
+        let value = one.remove_value("d").unwrap();
+        other.set_value("d", value);
+        assert!(!one.has("d"));
+        assert_eq!(other.get::<String>("d").unwrap(), "Hello, world!");
+        assert!(one.remove_value("d").is_none());
+    }
+
+    #[test]
+    fn set_aliased() {
+        let mut options = setup();
+        options.set("colour", "red");
+        options.set_aliased("color", &["colour", "colr"], "blue");
+        assert_eq!(options.get::<&str>("color"), Some("blue"));
+        assert_eq!(options.get::<&str>("colour"), Some("blue"));
+        assert_eq!(options.get_ref::<&str>("colr"), Some(&"blue"));
+        options.set("colr", "green");
+        assert_eq!(options.get::<&str>("color"), Some("green"));
+        *options.get_mut::<&str>("colour").unwrap() = "1s";
+        assert_eq!(options.get::<&str>("color"), Some("1s"));
+        assert_eq!(options.get_duration("colr"), Some(Duration::from_secs(1)));
+        assert_eq!(
+            options
+                .names()
+                .filter(|name| name.starts_with("col"))
+                .count(),
+            1
+        );
+        assert!(options.remove_value("colr").is_some());
+        assert!(!options.has("color"));
+    }
+
+    #[test]
+    fn set_optional() {
+        let mut options = setup();
+        options
+            .set_optional("a", None::<i32>)
+            .set_optional("z", Some(24))
+            .set_optional("y", None::<i32>);
+        assert_eq!(options.get::<i32>("a"), Some(42));
+        assert_eq!(options.get::<i32>("z"), Some(24));
+        assert!(!options.has("y"));
+    }
+
+    #[test]
+    fn update_or_insert() {
+        let mut options = Options::new();
+        for _ in 0..3 {
+            options.update_or_insert("a", 0, |value: &mut i32| *value += 1);
+        }
+        assert_eq!(options.get::<i32>("a"), Some(2));
+    }
+
+    #[test]
+    fn take_all() {
+        let mut options = setup();
+        let mut parameters = options.take_all();
+        parameters.sort_by(|one, other| one.0.cmp(&other.0));
+        let names = parameters.iter().map(|(name, _)| name).collect::<Vec<_>>();
+        assert_eq!(names, &["a", "b", "c", "d", "e"]);
+        assert_eq!(parameters[0].1.get::<i32>(), Some(42));
+        assert_eq!(options.names().count(), 0);
+    }
+
+    #[test]
+    fn set_many_boxed() {
+        let mut one = setup();
+        let mut other = Options::new();
+        other.set_many_boxed(one.drain().map(|(name, value)| (name, value.into_boxed())));
+        assert_eq!(one.names().count(), 0);
+        assert_eq!(other.names().count(), 5);
+        assert_eq!(other.get::<i32>("a"), Some(42));
+        assert_eq!(other.get::<String>("d").unwrap(), "Hello, world!");
+    }
+
+    #[test]
+    fn import() {
+        let mut options = setup();
+        let mut other = Options::new();
+        other.set("host", "localhost").set("port", 5432u16);
+        options.import("db.", other);
+        assert_eq!(options.get::<&str>("db.host"), Some("localhost"));
+        assert_eq!(options.get::<u16>("db.port"), Some(5432));
+        assert_eq!(options.names().count(), 7);
+    }
+
+    #[test]
+    fn apply_overrides() {
+        use super::ParseError;
+
+        let mut options = setup_nested();
+        let overrides = ["b=false".to_string(), "a.b.e=x=y".to_string()];
+        assert_eq!(options.apply_overrides(overrides), Ok(2));
+        assert_eq!(options.get::<String>("b"), Some("false".to_string()));
+        assert_eq!(options.get_path::<String>("a.b.e"), Some("x=y".to_string()));
+        assert_eq!(options.get_path::<i32>("a.b.c"), Some(42));
+
+        let overrides = ["c=d".to_string(), "=e".to_string(), "f".to_string()];
+        assert_eq!(
+            options.apply_overrides(overrides),
+            Err(ParseError { index: 1 })
+        );
+        assert!(!options.has("c"));
+    }
+
+    #[test]
+    fn try_merge() {
+        use super::Conflict;
+
+        let mut options = setup();
+        let mut other = Options::new();
+        other.set("a", 24).set("f", true);
+        assert_eq!(options.try_merge(other), Ok(()));
+        assert_eq!(options.get::<i32>("a"), Some(24));
+        assert_eq!(options.get::<bool>("f"), Some(true));
+
+        let mut other = Options::new();
+        other.set("a", "24").set("b", false);
+        assert_eq!(
+            options.try_merge(other),
+            Err(vec![Conflict {
+                name: "a".into(),
+                existing_type: "i32",
+                incoming_type: "&str",
+            }]),
+        );
+        assert_eq!(options.get::<i32>("a"), Some(24));
+        assert_eq!(options.get::<bool>("b"), Some(false));
+    }
+
+    #[test]
+    fn with_key_normalizer() {
+        let mut options = Options::with_key_normalizer(|name| name.replace('-', "_"));
+        options.set("max-size", 42).set("min_size", 24);
+        assert_eq!(options.get::<i32>("max_size"), Some(42));
+        assert_eq!(options.get::<i32>("max-size"), Some(42));
+        assert_eq!(options.get::<i32>("min-size"), Some(24));
+        *options.get_mut::<i32>("min-size").unwrap() += 1;
+        assert_eq!(options.get::<i32>("min_size"), Some(25));
+        let mut names = options.names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["max_size", "min_size"]);
+        assert!(options.remove_value("max-size").is_some());
+        assert!(!options.has("max_size"));
+
+        let mut options = Options::with_key_normalizer(str::to_lowercase);
+        options.set("Timeout", "1s").set("Port", 80);
+        assert_eq!(
+            options.get_duration("TIMEOUT"),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(options.try_get_ref::<i32>("PORT"), Ok(&80));
+        assert!(options.get_any("PORT").is_some());
+        assert_eq!(options.checked_get::<i32>("PORT"), Some(80));
+        assert!(options.has("PORT"));
+        options.rename_all(|name| format!("Server.{name}"));
+        let mut names = options.names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["server.port", "server.timeout"]);
+
+        let mut other = Options::new();
+        other.set("Host", "localhost");
+        options.import("Server.", other);
+        assert_eq!(options.get::<&str>("server.host"), Some("localhost"));
+
+        let mut top = Options::with_key_normalizer(str::to_lowercase);
+        top.set("Port", 8080);
+        assert_eq!(options.overlay(&top).get::<i32>("PORT"), Some(8080));
+
+        let mut options = Options::with_key_normalizer(str::to_lowercase);
+        options.set_aliased("Host", &["Server"], 1);
+        assert_eq!(options.get::<i32>("Server"), Some(1));
+        assert_eq!(*options.get_or_insert_ref("SERVER", 2), 1);
+        assert_eq!(options.names().collect::<Vec<_>>(), ["host"]);
+
+        options.set("A", 1).protect(&["A"]);
+        options.set("a", 2);
+        assert_eq!(options.get::<i32>("a"), Some(1));
+        options.unprotect(&["A"]);
+        options.set("a", 2);
+        assert_eq!(options.get::<i32>("a"), Some(2));
+
+        options.set("Old", 3);
+        options.canonicalize(&[("New", &["OLD"])]);
+        assert_eq!(options.get::<i32>("new"), Some(3));
+        assert!(!options.has("old"));
+    }
+
+    #[test]
+    fn set_checked() {
+        use super::{KeyError, KeyPolicy};
+
+        let mut options = Options::with_key_policy(KeyPolicy {
+            non_empty: true,
+            max_length: Some(4),
+            allowed: Some(|character| character.is_ascii_lowercase()),
+        });
+        assert!(options.set_checked("abcd", 42).is_ok());
+        assert_eq!(options.set_checked("", 42).err(), Some(KeyError::Empty));
+        assert_eq!(
+            options.set_checked("abcde", 42).err(),
+            Some(KeyError::TooLong {
+                length: 5,
+                max_length: 4,
+            }),
+        );
+        assert_eq!(
+            options.set_checked("aB", 42).err(),
+            Some(KeyError::InvalidCharacter('B')),
+        );
+        assert_eq!(options.names().collect::<Vec<_>>(), &["abcd"]);
+
+        let mut options = Options::new();
+        assert!(options.set_checked("", 42).is_ok());
+    }
+
+    #[test]
+    fn retain_typed() {
+        let mut options = setup();
+        options.set("f", 12).set("g", 69);
+        options.retain_typed(|_, value: &mut i32| *value > 24);
+        let mut names = options.names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, &["a", "b", "c", "d", "e", "g"]);
+    }
+
+    #[test]
+    fn set_clear() {
+        let mut base = setup();
+        let mut top = Options::new();
+        top.set_clear("a").set_clear("z").set("b", false);
+        let options = Options::from_layers([base.clone_cloneable(), top]);
+        assert!(!options.has("a"));
+        assert!(!options.has("z"));
+        assert!(!options.get::<bool>("b").unwrap());
+        assert!(options.has("c"));
+
+        let mut top = Options::new();
+        top.set_clear("a");
+        base.merge_cloning(&top);
+        assert!(!base.has("a"));
+    }
+
+    #[test]
+    fn merge_subset() {
+        let mut options = setup();
+        let mut other = Options::new();
+        other
+            .set("a", 69)
+            .set("b", false)
+            .set("c", "c")
+            .set("f", 'f');
+        options.merge_subset(other, &["a", "f", "z"]);
+        assert_eq!(options.get::<i32>("a"), Some(69));
+        assert!(options.get::<bool>("b").unwrap());
+        assert_eq!(options.get::<&str>("c"), Some("Hi, there!"));
+        assert_eq!(options.get::<char>("f"), Some('f'));
+        assert_eq!(options.names().count(), 6);
+    }
+
+    #[test]
+    fn merge_cloning() {
+        let mut template = Options::new();
+        template
+            .set("a", 69)
+            .set("f", "template".to_string())
+            .set("g", std::cell::Cell::new(1));
+        let mut one = setup();
+        one.merge_cloning(&template);
+        let mut other = Options::new();
+        other.merge_cloning(&template);
+        assert_eq!(one.get::<i32>("a"), Some(69));
+        assert!(one.get::<bool>("b").unwrap());
+        assert_eq!(one.get::<String>("f"), Some("template".to_string()));
+        assert!(!one.has("g"));
+        assert_eq!(other.get::<i32>("a"), Some(69));
+        assert_eq!(template.get::<i32>("a"), Some(69));
+        assert_eq!(template.get::<String>("f"), Some("template".to_string()));
+        assert!(template.has("g"));
+    }
+
+    #[test]
+    fn merge_appending() {
+        let mut one = Options::new();
+        one.set("include", vec!["a".to_string()])
+            .set("level", vec![1])
+            .set("debug", false);
+        let mut other = Options::new();
+        other
+            .set("include", vec!["b".to_string(), "c".to_string()])
+            .set("level", vec![2])
+            .set("debug", true)
+            .set("path", vec!["d".to_string()]);
+        one.merge_appending::<String>(other);
+        assert_eq!(
+            one.get::<Vec<String>>("include"),
+            Some(vec!["a".to_string(), "b".to_string(), "c".to_string()])
+        );
+        assert_eq!(one.get::<Vec<i32>>("level"), Some(vec![2]));
+        assert_eq!(one.get::<bool>("debug"), Some(true));
+        assert_eq!(one.get::<Vec<String>>("path"), Some(vec!["d".to_string()]));
+    }
+
+    #[test]
+    fn reconcile() {
+        use super::Reconciliation;
+
+        let mut options = setup_nested();
+        options.set("b", 1).set("c", "c").set("d", 'd');
+        let mut target = setup_nested();
+        target.get_path_mut::<Options>("a.b").unwrap().set("c", 69);
+        target.set("b", 2).set("c", "c").set("e", true);
+        let reconciliation = options.reconcile(&target);
+        assert_eq!(
+            reconciliation,
+            Reconciliation {
+                added: vec!["e".into()],
+                removed: vec!["d".into()],
+                updated: vec!["a".into(), "b".into()],
+            }
+        );
+        assert_eq!(options.get_path::<i32>("a.b.c"), Some(69));
+        assert_eq!(options.get_path::<bool>("a.d"), Some(true));
+        assert_eq!(options.get::<i32>("b"), Some(2));
+        assert!(!options.has("d"));
+        assert_eq!(options.get::<bool>("e"), Some(true));
+        assert_eq!(options.reconcile(&target), Reconciliation::default());
+    }
+
+    #[test]
+    fn batch() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        let mut options = setup();
+        options.set("f", Duration::from_secs(1));
+        let result = options.batch(|options| {
+            options.set("a", 24).set("z", true).remove_value("b");
+            options.set("f", ());
+            Err("failed")
+        });
+        assert_eq!(result, Err("failed"));
+        let mut names = options.names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, &["a", "b", "c", "d", "e", "f"]);
+        assert_eq!(options.get::<i32>("a"), Some(42));
+        assert_eq!(options.get::<bool>("b"), Some(true));
+        assert_eq!(options.get::<Duration>("f"), Some(Duration::from_secs(1)));
+
+        let result = options.batch(|options| {
+            options.set("a", 24);
+            Ok::<_, ()>(())
+        });
+        assert!(result.is_ok());
+        assert_eq!(options.get::<i32>("a"), Some(24));
+
+        struct Handler(usize);
+
+        let forced = Rc::new(Cell::new(false));
+        let mut options = Options::new();
+        options.set("h", Handler(1)).set("i", Handler(1));
+        {
+            let forced = forced.clone();
+            options.set_lazy("z", move || forced.set(true));
+        }
+        let version = options.version();
+        let result = options.batch(|options| {
+            options.set("h", Handler(2)).set("z", 1);
+            options.get_mut::<Handler>("i").unwrap().0 = 2;
+            options.clear();
+            options.set("y", 1);
+            Err(())
+        });
+        assert!(result.is_err());
+        assert_eq!(options.get_ref::<Handler>("h").unwrap().0, 1);
+        assert_eq!(options.get_ref::<Handler>("i").unwrap().0, 2);
+        assert!(!options.has("y"));
+        assert!(!forced.get());
+        assert_eq!(options.get::<()>("z"), Some(()));
+        assert!(forced.get());
+        let mut names = options.changed_since(version);
+        names.sort();
+        assert_eq!(names, ["h", "i", "z"]);
+
+        let mut options = setup();
+        options.checkpoint("first");
+        let result = options.batch(|options| {
+            options.set("a", 24);
+            options.batch(|options| {
+                options.set("b", false);
+                Ok::<_, ()>(())
+            })
+        });
+        assert!(result.is_ok());
+        assert_eq!(options.get::<i32>("a"), Some(24));
+        assert_eq!(options.get::<bool>("b"), Some(false));
+        assert!(options.rollback("first"));
+        assert_eq!(options.get::<i32>("a"), Some(42));
+        assert_eq!(options.get::<bool>("b"), Some(true));
+    }
+
+    #[test]
+    fn checkpoint() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        let mut options = setup();
+        options.checkpoint("first");
+        options.set("a", 24).remove_value("b");
+        options.checkpoint("second");
+        options.set("z", true);
+        assert!(options.rollback("first"));
+        let mut names = options.names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, &["a", "b", "c", "d", "e"]);
+        assert_eq!(options.get::<i32>("a"), Some(42));
+        assert_eq!(options.get::<bool>("b"), Some(true));
+        assert!(!options.rollback("second"));
+        assert!(!options.rollback("first"));
+
+        options.checkpoint("first");
+        options.checkpoint("second");
+        assert!(options.rollback("second"));
+        assert!(options.rollback("first"));
+
+        let forced = Rc::new(Cell::new(false));
+        {
+            let forced = forced.clone();
+            options.set_lazy("z", move || forced.set(true));
+        }
+        options.checkpoint("lazy");
+        assert!(!forced.get());
+        options.set("z", 1);
+        assert!(options.rollback("lazy"));
+        assert!(!forced.get());
+
+        struct Handler(usize);
+
+        let mut options = Options::new();
+        options.set("h", Handler(1)).set("i", Handler(1));
+        options.checkpoint("first");
+        options.set("h", Handler(2)).remove_value("i");
+        options.set("i", Handler(2));
+        options.checkpoint("second");
+        options.clear();
+        assert!(options.rollback("second"));
+        assert_eq!(options.get_ref::<Handler>("h").unwrap().0, 2);
+        assert_eq!(options.get_ref::<Handler>("i").unwrap().0, 2);
+        assert!(options.rollback("first"));
+        assert_eq!(options.get_ref::<Handler>("h").unwrap().0, 1);
+        assert!(!options.has("i"));
+
+        options.checkpoint("outer");
+        let result = options.batch(|options| {
+            options.set("a", 1);
+            assert!(!options.rollback("outer"));
+            options.checkpoint("inner");
+            options.set("b", 1);
+            assert!(options.rollback("inner"));
+            assert!(!options.has("b"));
+            Ok::<_, ()>(())
+        });
+        assert!(result.is_ok());
+        assert!(options.has("a"));
+        assert!(options.rollback("outer"));
+        assert!(!options.has("a"));
+    }
+
+    #[test]
+    fn deep_merge() {
+        let mut one = Options::new();
+        let mut db = Options::new();
+        db.set("host", "localhost").set("port", 5432);
+        one.set("db", db).set("debug", false);
+
+        let mut other = Options::new();
+        let mut db = Options::new();
+        db.set("port", 6432).set("user", "admin");
+        other.set("db", db).set("debug", true);
+
+        one.deep_merge(other);
+        assert_eq!(one.get_path::<&str>("db.host"), Some("localhost"));
+        assert_eq!(one.get_path::<i32>("db.port"), Some(6432));
+        assert_eq!(one.get_path::<&str>("db.user"), Some("admin"));
+        assert_eq!(one.get::<bool>("debug"), Some(true));
+
+        let mut other = Options::new();
+        other.set("db", 42);
+        one.deep_merge(other);
+        assert_eq!(one.get::<i32>("db"), Some(42));
+    }
+
+    #[test]
+    fn merge_using() {
+        use super::{Conflict, MergeStrategy};
+
+        fn layers() -> (Options, Options) {
+            let mut one = Options::new();
+            let mut db = Options::new();
+            db.set("host", "localhost");
+            one.set("a", 42).set("b", true).set("db", db);
+            let mut other = Options::new();
+            let mut db = Options::new();
+            db.set("port", 5432);
+            other
+                .set("a", 24)
+                .set("b", "yes")
+                .set("c", 69)
+                .set("db", db);
+            (one, other)
+        }
+
+        let (mut one, other) = layers();
+        assert!(one.merge_using(other, MergeStrategy::Overwrite).is_ok());
+        assert_eq!(one.get::<i32>("a"), Some(24));
+        assert_eq!(one.get::<&str>("b"), Some("yes"));
+        assert_eq!(one.get::<i32>("c"), Some(69));
+        assert!(!one.get_ref::<Options>("db").unwrap().has("host"));
+
+        let (mut one, other) = layers();
+        assert!(one.merge_using(other, MergeStrategy::KeepExisting).is_ok());
+        assert_eq!(one.get::<i32>("a"), Some(42));
+        assert_eq!(one.get::<bool>("b"), Some(true));
+        assert_eq!(one.get::<i32>("c"), Some(69));
+        assert!(!one.get_ref::<Options>("db").unwrap().has("port"));
+
+        let (mut one, other) = layers();
+        let conflicts = one.merge_using(other, MergeStrategy::ErrorOnConflict);
+        assert_eq!(
+            conflicts,
+            Err(vec![Conflict {
+                name: "b".into(),
+                existing_type: "bool",
+                incoming_type: "&str",
+            }]),
+        );
+        assert_eq!(one.get::<i32>("a"), Some(24));
+        assert_eq!(one.get::<bool>("b"), Some(true));
+
+        let (mut one, other) = layers();
+        assert!(one.merge_using(other, MergeStrategy::DeepMerge).is_ok());
+        assert_eq!(one.get_path::<&str>("db.host"), Some("localhost"));
+        assert_eq!(one.get_path::<i32>("db.port"), Some(5432));
+        assert_eq!(one.get::<&str>("b"), Some("yes"));
+    }
+
+    #[test]
+    fn merge_tracked() {
+        let mut options = Options::new();
+        options.set("debug", false);
+        let mut file = Options::new();
+        file.set("host", "localhost").set("port", 80);
+        let mut names = options.merge_tracked(file, "file");
+        names.sort();
+        assert_eq!(names, &["host", "port"]);
+        let mut arguments = Options::new();
+        arguments.set("port", 8080);
+        assert_eq!(options.merge_tracked(arguments, "arguments"), &["port"]);
+        assert_eq!(options.get::<i32>("port"), Some(8080));
+        assert_eq!(options.source_of("port"), Some("arguments"));
+        assert_eq!(options.source_of("host"), Some("file"));
+        assert_eq!(options.source_of("debug"), None);
+        assert_eq!(options.flatten().source_of("host"), Some("file"));
+        for (_, value) in &mut options {
+            value.set(0);
+        }
+        assert_eq!(options.source_of("port"), Some("arguments"));
+        options.set("host", "example.com");
+        assert_eq!(options.source_of("host"), None);
+    }
+
+    #[test]
+    fn retain_paths() {
+        let mut options = setup_nested();
+        let db = options.path_entry_or_insert::<Options>("db");
+        db.set("host", "localhost").set("port", 5432);
+        db.path_entry_or_insert::<Options>("pool").set("size", 4);
+        options.set("debug", true).set("dbx", 1);
+        options.retain_paths(|path| path.starts_with("db.") && path != "db.port");
+        let paths = options
+            .walk_leaves()
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect::<Vec<_>>();
+        assert_eq!(paths, &["db.host", "db.pool.size"]);
+        assert!(!options.has("a"));
+
+        options.retain_paths(|path| path == "db.host");
+        assert!(!options.has_path("db.pool"));
+        options.retain_paths(|_| false);
+        assert_eq!(options.names().count(), 0);
+    }
+
+    #[test]
+    fn retain_reporting() {
+        let mut options = setup();
+        let mut removed = options.retain_reporting(|name, _| name < "c");
+        removed.sort();
+        assert_eq!(removed, &["c", "d", "e"]);
+        let mut names = options.names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, &["a", "b"]);
+        assert!(options.retain_reporting(|_, _| true).is_empty());
+    }
+
+    #[test]
+    fn retain_newer_than() {
+        let mut options = setup();
+        assert_eq!(options.age_of("a"), None);
+        assert_eq!(options.retain_newer_than(std::time::Instant::now()), 0);
+
+        let mut options = Options::with_timestamps();
+        options
+            .set("a", 42)
+            .set("b", true)
+            .set("c", "Hi, there!")
+            .set("d", "Hello, world!".to_string())
+            .set("e", vec![4u8, 2u8]);
+        std::thread::sleep(Duration::from_millis(10));
+        let cutoff = std::time::Instant::now();
+        options.set("f", 69).set("a", 24);
+        *options.get_mut::<bool>("b").unwrap() = false;
+        assert!(options.age_of("c").unwrap() >= Duration::from_millis(10));
+        assert!(options.age_of("f").unwrap() < options.age_of("c").unwrap());
+        assert_eq!(options.age_of("z"), None);
+        assert_eq!(options.retain_newer_than(cutoff), 4);
+        let mut names = options.names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, &["a", "f"]);
+        let mut options = Options::from_str_pairs(vec![("a".into(), "b".into())]);
+        assert_eq!(options.age_of("a"), None);
+        assert_eq!(options.retain_newer_than(std::time::Instant::now()), 0);
+    }
+
+    #[test]
+    fn retain_keys_of() {
+        let mut options = setup();
+        let mut other = Options::new();
+        other.set("a", ()).set("c", ()).set("z", ());
+        assert_eq!(options.retain_keys_of(&other), 3);
+        let mut names = options.names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, &["a", "c"]);
+        assert_eq!(options.get::<i32>("a"), Some(42));
+    }
+
+    #[test]
+    fn prune_empty() {
+        let mut options = setup();
+        options
+            .set("f", String::new())
+            .set("g", "")
+            .set("h", Vec::<u8>::new())
+            .set("i", Vec::<String>::new())
+            .set("j", Options::new())
+            .set("k", setup())
+            .set("l", ());
+        assert_eq!(options.prune_empty(), 5);
+        let mut names = options.names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, &["a", "b", "c", "d", "e", "k", "l"]);
+    }
+
+    #[test]
+    fn prune_defaults() {
+        let mut options = setup();
+        options.set("f", 0).set("g", 0).set("h", 0u8).set("i", "");
+        assert_eq!(options.prune_defaults::<i32>(), 2);
+        let mut names = options.names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, &["a", "b", "c", "d", "e", "h", "i"]);
+        assert_eq!(options.prune_defaults::<i32>(), 0);
+    }
+
+    #[test]
+    fn canonicalize() {
+        let mut options = Options::new();
+        options.set("colour", "red").set("other", 42);
+        options.canonicalize(&[("color", &["colour", "colr"])]);
+        assert_eq!(options.get::<&str>("color"), Some("red"));
+        assert!(!options.has("colour"));
+        assert!(!options.has("colr"));
+        assert_eq!(options.get::<i32>("other"), Some(42));
+
+        options.set("colr", "blue");
+        options.canonicalize(&[("color", &["colour", "colr"])]);
+        assert_eq!(options.get::<&str>("color"), Some("red"));
+        assert!(!options.has("colr"));
+    }
+
+    #[test]
+    fn intern_static() {
+        use std::borrow::Cow;
+
+        let mut options = setup();
+        options.intern_static(&["a", "b", "z"]);
+        for (name, _) in &options {
+            assert_eq!(
+                matches!(name, Cow::Borrowed(_)),
+                ["a", "b"].contains(&&**name)
+            );
+        }
+        assert_eq!(options.get::<i32>("a"), Some(42));
+        assert_eq!(options.get::<bool>("b"), Some(true));
+        assert_eq!(options.names().count(), 5);
+    }
+
+    #[test]
+    fn debug_assert_schema() {
+        use std::any::TypeId;
+
+        let options = setup();
+        options.debug_assert_schema(&[("a", TypeId::of::<i32>()), ("b", TypeId::of::<bool>())]);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "the parameter \"z\" is missing")]
+    fn debug_assert_schema_missing() {
+        use std::any::TypeId;
+
+        setup().debug_assert_schema(&[("a", TypeId::of::<i32>()), ("z", TypeId::of::<i32>())]);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "the parameter \"a\" is of type i32")]
+    fn debug_assert_schema_mismatch() {
+        use std::any::TypeId;
+
+        setup().debug_assert_schema(&[("a", TypeId::of::<bool>())]);
+    }
+
+    #[test]
+    fn validate_each() {
+        let options = setup();
+        let port = |value: &super::Value| match value.get_ref::<i32>() {
+            Some(1..=65535) => Ok(()),
+            _ => Err("the port is out of range".to_string()),
+        };
+        let flag = |value: &super::Value| match value.get_ref::<bool>() {
+            Some(_) => Ok(()),
+            _ => Err("the flag is not a boolean".to_string()),
+        };
+        assert_eq!(options.validate_each(&[("a", &port), ("b", &flag)]), Ok(()));
+        assert_eq!(
+            options.validate_each(&[("a", &port), ("c", &flag), ("z", &flag)]),
+            Err(vec![
+                ("c".to_string(), "the flag is not a boolean".to_string()),
+                ("z".to_string(), "the parameter is missing".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn count() {
+        let mut options = setup();
+        options.set("feature.a", true).set("feature.b", false);
+        assert_eq!(options.count(|name, _| name.starts_with("feature.")), 2);
+        assert_eq!(
+            options.count(|_, value| value.get_ref::<bool>().is_some()),
+            3
+        );
+        assert_eq!(options.count(|_, _| false), 0);
+    }
+
+    #[test]
+    fn has() {
+        let options = setup();
+        assert!(options.has("a"));
+        assert!(!options.has("z"));
+    }
+
+    #[test]
+    fn changed_since() {
+        let mut options = setup();
+        let version = options.version();
+        assert!(options.changed_since(version).is_empty());
+        options.set("f", 1).set("b", false);
+        let middle = options.version();
+        *options.get_mut::<i32>("a").unwrap() += 1;
+        options.set("g", 2);
+        assert_eq!(options.changed_since(version), &["a", "b", "f", "g"]);
+        assert_eq!(options.changed_since(middle), &["a", "g"]);
+        assert_eq!(options.changed_since(0).len(), 7);
+        assert!(options.changed_since(options.version()).is_empty());
+
+        let version = options.version();
+        let (name, _) = options.iter_mut().next().unwrap();
+        let name = name.clone();
+        assert_eq!(options.changed_since(version), [&name]);
+    }
+
+    #[test]
+    fn path_version() {
+        let mut options = setup_nested();
+        options.path_entry_or_insert::<Options>("x").set("y", 1);
+        let before = ["", "a", "a.b", "x"].map(|path| options.path_version(path).unwrap());
+        *options.get_path_mut::<i32>("a.b.c").unwrap() += 1;
+        let after = ["", "a", "a.b", "x"].map(|path| options.path_version(path).unwrap());
+        assert!(after[0] > before[0]);
+        assert!(after[1] > before[1]);
+        assert!(after[2] > before[2]);
+        assert_eq!(after[3], before[3]);
+        assert!(options.path_version("a.d").is_none());
+    }
+
+    #[test]
+    fn version() {
+        let mut options = Options::new();
+        let mut version = options.version();
+        macro_rules! changed(
+            ($expected:expr) => ({
+                assert_eq!(options.version() > version, $expected);
+                version = options.version();
+            });
+        );
+
+        options.set("a", 42);
+        changed!(true);
+        let _ = options.get::<i32>("a");
+        let _ = options.get_ref::<i32>("a");
+        let _ = options.has("a");
+        let _ = options.names().count();
+        changed!(false);
+        *options.get_mut::<i32>("a").unwrap() = 24;
+        changed!(true);
+        assert!(options.get_mut::<bool>("a").is_none());
+        changed!(false);
+        options.remove_value("a");
+        changed!(true);
+        options.remove_value("a");
+        changed!(false);
+        options.set("b", true).clear();
+        changed!(true);
+        options.clear();
+        options.retain_typed::<i32, _>(|_, _| true);
+        options.retain_paths(|_| true);
+        options.rename_all(str::to_uppercase);
+        options.drain();
+        options.iter_mut();
+        options.for_each_mut(|_, _| {});
+        changed!(false);
+        options.set("a", 1).protect(&["a"]);
+        changed!(true);
+        options.drain();
+        changed!(false);
+        options.unprotect(&["a"]);
+        options.drain();
+        changed!(true);
+
+        options.set("c", 1).set("d", setup_nested());
+        changed!(true);
+        options.retain_typed::<bool, _>(|_, _| false);
+        options.retain_paths(|_| true);
+        changed!(false);
+        options.retain_typed::<i32, _>(|_, _| true);
+        assert_eq!(options.changed_since(version), ["c"]);
+        changed!(true);
+        options.retain_paths(|path| path != "d.a.d");
+        assert_eq!(options.changed_since(version), ["d"]);
+        changed!(true);
+        let mut target = Options::new();
+        target.set("c", 1).set("d", setup_nested());
+        assert_eq!(options.reconcile(&target).updated, ["d"]);
+        assert_eq!(options.changed_since(version), ["d"]);
+    }
+
+    #[test]
+    fn set_if_changed() {
+        let mut options = Options::new();
+        assert!(options.set_if_changed("a", 42));
+        let version = options.version();
+        assert!(!options.set_if_changed("a", 42));
+        assert_eq!(options.version(), version);
+        assert!(options.set_if_changed("a", 42u8));
+        assert!(options.version() > version);
+        assert_eq!(options.get::<u8>("a"), Some(42));
+    }
+
+    #[test]
+    fn names() {
+        let options = setup();
+        let mut names = options.names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, &["a", "b", "c", "d", "e"]);
+    }
+
+    #[test]
+    fn key_difference() {
+        let one = setup();
+        let mut other = Options::new();
+        other.set("a", 1).set("c", 2).set("z", 3);
+        let mut names = one.key_difference(&other).collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, &["b", "d", "e"]);
+
+        let mut other = Options::new();
+        other.set("z", 3);
+        assert_eq!(one.key_difference(&other).count(), 5);
+    }
+
+    #[test]
+    fn key_intersection() {
+        let one = setup();
+        let mut other = Options::new();
+        other.set("a", 1).set("c", 2).set("z", 3);
+        let mut names = one.key_intersection(&other).collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, &["a", "c"]);
+
+        let mut other = Options::new();
+        other.set("z", 3);
+        assert_eq!(one.key_intersection(&other).count(), 0);
+    }
+
+    #[test]
+    fn any_name() {
+        assert_eq!(Options::new().any_name(), None);
+        let options = setup();
+        assert!(options.has(options.any_name().unwrap()));
+    }
+
+    #[test]
+    fn glob() {
+        let mut options = setup_nested();
+        options.set("timeout", 1);
+        let mut db = Options::new();
+        db.set("timeout", 2).set("host", "localhost");
+        let mut replica = Options::new();
+        replica.set("timeout", 3).set("timeouts", 4);
+        db.set("replica", replica);
+        options.set("db", db);
+        macro_rules! test(
+            ($pattern:expr, $expected:expr) => ({
+                let paths = options.glob($pattern).into_iter().map(|(path, _)| path).collect::<Vec<_>>();
+                assert_eq!(paths, $expected);
+            });
+        );
+
+        test!(
+            "**.timeout",
+            ["db.replica.timeout", "db.timeout", "timeout"]
+        );
+        test!("db.*", ["db.host", "db.timeout"]);
+        test!("db.*.timeout?", ["db.replica.timeouts"]);
+        test!(
+            "db.**",
+            [
+                "db.host",
+                "db.replica.timeout",
+                "db.replica.timeouts",
+                "db.timeout"
+            ]
+        );
+        test!("a.**.c", ["a.b.c"]);
+        options.path_entry_or_insert::<Options>("a").set("x.y", 5);
+        test!(r"a.x\.*", [r"a.x\.y"]);
+        test!("a.x.*", [] as [&str; 0]);
+        test!("a.*", ["a.d", r"a.x\.y"]);
+        assert!(options.glob("?").is_empty());
+        assert_eq!(
+            options.glob("a.?").first().unwrap().1.get::<bool>(),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn names_matching() {
+        let mut options = Options::new();
+        options
+            .set("db.timeout", 1)
+            .set("db.host", 2)
+            .set("http.timeout", 3)
+            .set("timeout", 4)
+            .set("db1", 5)
+            .set("db", 6);
+        macro_rules! test(
+            ($pattern:expr, $expected:expr) => ({
+                let mut names = options.names_matching($pattern).collect::<Vec<_>>();
+                names.sort();
+                assert_eq!(names, $expected);
+            });
+        );
+
+        test!("db.*", &["db.host", "db.timeout"]);
+        test!("*.timeout", &["db.timeout", "http.timeout"]);
+        test!("d*t", &["db.host", "db.timeout"]);
+        test!("db?", &["db1"]);
+        test!(
+            "*",
+            &[
+                "db",
+                "db.host",
+                "db.timeout",
+                "db1",
+                "http.timeout",
+                "timeout"
+            ]
+        );
+        test!("timeout", &["timeout"]);
+        test!("x*", &[] as &[&str]);
+    }
+
+    #[test]
+    fn sorted_names_cached() {
+        let mut options = setup();
+        let pointer = options.sorted_names_cached().as_ptr();
+        assert_eq!(options.sorted_names_cached(), &["a", "b", "c", "d", "e"]);
+        assert_eq!(options.sorted_names_cached().as_ptr(), pointer);
+        let _ = options.get::<i32>("a");
+        assert_eq!(options.sorted_names_cached().as_ptr(), pointer);
+        options.set("0", ()).remove_value("c");
+        assert_eq!(options.sorted_names_cached(), &["0", "a", "b", "d", "e"]);
+    }
+
+    #[test]
+    fn names_sorted_by() {
+        let mut options = Options::new();
+        options.set("item10", 10).set("item2", 2).set("item1", 1);
+        let names = options.names_sorted_by(|one, other| {
+            let one = one.trim_start_matches("item").parse::<usize>().unwrap();
+            let other = other.trim_start_matches("item").parse::<usize>().unwrap();
+            one.cmp(&other)
+        });
+        assert_eq!(names, &["item1", "item2", "item10"]);
+    }
+
+    fn capture(options: &mut Options) -> impl Fn() -> Vec<String> {
+        let warnings = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let sink = std::rc::Rc::clone(&warnings);
+        options.set_warning_hook(move |message| sink.borrow_mut().push(message.to_string()));
+        move || warnings.borrow_mut().drain(..).collect()
+    }
+
+    fn setup() -> Options {
+        let mut options = Options::new();
+
+        options
+            .set("a", 42)
+            .set("b", true)
             .set("c", "Hi, there!")
             .set("d", "Hello, world!".to_string())
             .set("e", vec![4u8, 2u8]);
 
         options
     }
+
+    fn setup_nested() -> Options {
+        let mut inner = Options::new();
+        inner.set("c", 42);
+        let mut outer = Options::new();
+        outer.set("b", inner).set("d", true);
+        let mut options = Options::new();
+        options.set("a", outer);
+        options
+    }
 }
@@ -0,0 +1,86 @@
+//! Parsing of values given as strings.
+
+use std::time::Duration;
+
+/// Parse a duration such as `"500ms"` or `"1.5s"`.
+pub fn duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let split = value.find(|character: char| !character.is_ascii_digit() && character != '.')?;
+    let (number, unit) = value.split_at(split);
+    if number.is_empty() || number.starts_with('.') || number.ends_with('.') {
+        return None;
+    }
+    let nanoseconds: u64 = match unit {
+        "ns" => 1,
+        "us" | "µs" => 1_000,
+        "ms" => 1_000_000,
+        "s" => 1_000_000_000,
+        "m" => 60_000_000_000,
+        "h" => 3_600_000_000_000,
+        _ => return None,
+    };
+    if let Ok(number) = number.parse::<u64>() {
+        return Some(Duration::from_nanos(number.checked_mul(nanoseconds)?));
+    }
+    let number = number.parse::<f64>().ok()?;
+    Duration::try_from_secs_f64(number * nanoseconds as f64 / 1e9).ok()
+}
+
+/// Parse bytes written as hexadecimal digits such as `"0aff"`.
+pub fn hex(value: &str) -> Option<Vec<u8>> {
+    let value = value.trim();
+    if !value.len().is_multiple_of(2) || !value.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&value[i..i + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    #[test]
+    fn duration() {
+        macro_rules! test(
+            ($value:expr, $expected:expr) => (
+                assert_eq!(super::duration($value), $expected)
+            );
+        );
+
+        test!("1500ms", Some(Duration::from_millis(1500)));
+        test!("2s", Some(Duration::from_secs(2)));
+        test!("1.5s", Some(Duration::from_millis(1500)));
+        test!("5m", Some(Duration::from_secs(300)));
+        test!("1h", Some(Duration::from_secs(3600)));
+        test!("10us", Some(Duration::from_micros(10)));
+        test!("10ns", Some(Duration::from_nanos(10)));
+        test!(" 2s ", Some(Duration::from_secs(2)));
+        test!("2", None);
+        test!("s", None);
+        test!("2 s", None);
+        test!("2d", None);
+        test!(".5s", None);
+        test!("-2s", None);
+    }
+
+    #[test]
+    fn hex() {
+        macro_rules! test(
+            ($value:expr, $expected:expr) => (
+                assert_eq!(super::hex($value), $expected)
+            );
+        );
+
+        test!("", Some(vec![]));
+        test!("0aff", Some(vec![0x0a, 0xff]));
+        test!("DEADbeef", Some(vec![0xde, 0xad, 0xbe, 0xef]));
+        test!(" 00 ", Some(vec![0]));
+        test!("abc", None);
+        test!("zz", None);
+        test!("+1", None);
+        test!("éa", None);
+    }
+}
@@ -0,0 +1,176 @@
+//! Paths to nested parameters.
+//!
+//! A path is a sequence of names separated by a separator, which is a dot for
+//! the path-based methods of `Options`. Segments can be empty; for instance,
+//! `"a..b"` consists of `"a"`, `""`, and `"b"`.
+//!
+//! To represent names containing the separator, the escaping variants treat a
+//! backslash followed by the separator as the separator itself and a double
+//! backslash as a single backslash. A backslash followed by any other
+//! character, or ending the path, is kept as it is. For instance, with a dot as
+//! the separator, `r"a\.b.c"` consists of `"a.b"` and `"c"`. The path-based
+//! methods of `Options` recognize escaping.
+
+use std::borrow::Cow;
+
+/// Split a path into segments.
+pub fn parse_path(path: &str, separator: char) -> Vec<&str> {
+    path.split(separator).collect()
+}
+
+/// Split a path into segments taking escaping into account.
+///
+/// The segments without escape sequences are borrowed.
+pub fn parse_path_escaped(path: &str, separator: char) -> Vec<Cow<'_, str>> {
+    let mut segments = Vec::new();
+    let mut start = 0;
+    let mut segment: Option<String> = None;
+    let mut characters = path.char_indices().peekable();
+    while let Some((i, character)) = characters.next() {
+        if character == separator {
+            segments.push(match segment.take() {
+                Some(segment) => Cow::Owned(segment),
+                _ => Cow::Borrowed(&path[start..i]),
+            });
+            start = i + character.len_utf8();
+        } else if character == '\\' {
+            match characters.peek() {
+                Some(&(_, next)) if next == separator || next == '\\' => {
+                    characters.next();
+                    segment
+                        .get_or_insert_with(|| path[start..i].to_string())
+                        .push(next);
+                }
+                _ => {
+                    if let Some(segment) = &mut segment {
+                        segment.push(character);
+                    }
+                }
+            }
+        } else if let Some(segment) = &mut segment {
+            segment.push(character);
+        }
+    }
+    segments.push(match segment {
+        Some(segment) => Cow::Owned(segment),
+        _ => Cow::Borrowed(&path[start..]),
+    });
+    segments
+}
+
+/// Escape a name so that it forms a single segment of a path.
+pub fn escape_segment(name: &str, separator: char) -> Cow<'_, str> {
+    if !name.contains([separator, '\\']) {
+        return Cow::Borrowed(name);
+    }
+    let mut escaped = String::with_capacity(name.len() + 1);
+    for character in name.chars() {
+        if character == separator || character == '\\' {
+            escaped.push('\\');
+        }
+        escaped.push(character);
+    }
+    Cow::Owned(escaped)
+}
+
+/// Split off the first segment taking escaping into account.
+///
+/// The segment is unescaped, and the rest, if any, is left escaped.
+pub(crate) fn split_first(path: &str, separator: char) -> (Cow<'_, str>, Option<&str>) {
+    match separators(path, separator).next() {
+        Some(i) => (
+            unescape(&path[..i], separator),
+            Some(&path[i + separator.len_utf8()..]),
+        ),
+        _ => (unescape(path, separator), None),
+    }
+}
+
+/// Split off the last segment taking escaping into account.
+///
+/// The segment is unescaped, and the rest, if any, is left escaped.
+pub(crate) fn split_last(path: &str, separator: char) -> (Option<&str>, Cow<'_, str>) {
+    match separators(path, separator).last() {
+        Some(i) => (
+            Some(&path[..i]),
+            unescape(&path[i + separator.len_utf8()..], separator),
+        ),
+        _ => (None, unescape(path, separator)),
+    }
+}
+
+fn separators(path: &str, separator: char) -> impl Iterator<Item = usize> + '_ {
+    let mut characters = path.char_indices().peekable();
+    std::iter::from_fn(move || {
+        while let Some((i, character)) = characters.next() {
+            if character == separator {
+                return Some(i);
+            }
+            if character == '\\' {
+                characters.next_if(|&(_, next)| next == separator || next == '\\');
+            }
+        }
+        None
+    })
+}
+
+fn unescape(segment: &str, separator: char) -> Cow<'_, str> {
+    match segment.contains('\\') {
+        true => parse_path_escaped(segment, separator).swap_remove(0),
+        _ => Cow::Borrowed(segment),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn parse_path() {
+        assert_eq!(super::parse_path("a.b.c", '.'), &["a", "b", "c"]);
+        assert_eq!(super::parse_path("a", '.'), &["a"]);
+        assert_eq!(super::parse_path("a..b.", '.'), &["a", "", "b", ""]);
+        assert_eq!(super::parse_path("", '.'), &[""]);
+        assert_eq!(super::parse_path("a/b.c", '/'), &["a", "b.c"]);
+    }
+
+    #[test]
+    fn parse_path_escaped() {
+        macro_rules! test(
+            ($path:expr, $expected:expr) => (
+                assert_eq!(super::parse_path_escaped($path, '.'), $expected)
+            );
+        );
+
+        test!("a.b.c", &["a", "b", "c"]);
+        test!(r"a\.b.c", &["a.b", "c"]);
+        test!(r"a\\.b", &[r"a\", "b"]);
+        test!(r"a\\\.b", &[r"a\.b"]);
+        test!(r"a\b.c\", &[r"a\b", r"c\"]);
+        test!("a..b", &["a", "", "b"]);
+        test!(r"\..", &[".", ""]);
+    }
+
+    #[test]
+    fn split() {
+        assert_eq!(
+            super::split_first(r"a\.b.c.d", '.'),
+            ("a.b".into(), Some("c.d"))
+        );
+        assert_eq!(super::split_first(r"a\.b", '.'), ("a.b".into(), None));
+        assert_eq!(super::split_last(r"a.b\.c", '.'), (Some("a"), "b.c".into()));
+        assert_eq!(
+            super::split_last(r"a\.b.c", '.'),
+            (Some(r"a\.b"), "c".into())
+        );
+        assert_eq!(super::split_last(r"a\\.b", '.'), (Some(r"a\\"), "b".into()));
+        assert_eq!(super::split_last("a", '.'), (None, "a".into()));
+    }
+
+    #[test]
+    fn escape_segment() {
+        for name in ["a", "a.b", r"a\b", r"a\.b\", ""] {
+            let escaped = super::escape_segment(name, '.');
+            let path = format!("{escaped}.c");
+            assert_eq!(super::parse_path_escaped(&path, '.'), &[name, "c"]);
+        }
+    }
+}
@@ -0,0 +1,80 @@
+//! Properties files, which consist of lines of the form `name=value`.
+
+use std::io::{Result, Write};
+
+/// Write a line with a name and a value escaped.
+///
+/// In both, a backslash, an equals sign, a line feed, a carriage return, and a
+/// tab are written as `\\`, `\=`, `\n`, `\r`, and `\t`, respectively. Spaces at
+/// the start or the end are written as `\ `, and so is `#` at the start of the
+/// name, so that the line is not mistaken for a comment.
+pub fn write<W: Write>(writer: &mut W, name: &str, value: &str) -> Result<()> {
+    writeln!(writer, "{}={}", escape(name, true), escape(value, false))
+}
+
+/// Read a name and a value from a line.
+///
+/// Empty lines, lines of whitespace, and lines whose first character other than
+/// whitespace is `#` yield `Ok(None)`. The name and value are separated by the
+/// first unescaped equals sign, and unescaped whitespace around them is
+/// trimmed. The escape sequences are those of `write`; a backslash followed by
+/// any other character stands for the character itself. A line without an
+/// unescaped equals sign is an error.
+pub fn read(line: &str) -> std::result::Result<Option<(String, String)>, ()> {
+    let line = line.trim_start_matches([' ', '\t']);
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    let mut name = None;
+    let mut part = Vec::new();
+    let mut characters = line.chars();
+    while let Some(character) = characters.next() {
+        match character {
+            '\\' => part.push((
+                match characters.next() {
+                    Some('n') => '\n',
+                    Some('r') => '\r',
+                    Some('t') => '\t',
+                    Some(character) => character,
+                    _ => '\\',
+                },
+                true,
+            )),
+            '=' if name.is_none() => name = Some(trim(std::mem::take(&mut part))),
+            character => part.push((character, false)),
+        }
+    }
+    match name {
+        Some(name) => Ok(Some((name, trim(part)))),
+        _ => Err(()),
+    }
+}
+
+fn trim(part: Vec<(char, bool)>) -> String {
+    let space =
+        |&&(character, escaped): &&(char, bool)| !escaped && matches!(character, ' ' | '\t');
+    let start = part.iter().take_while(space).count();
+    let end = part.len() - part.iter().rev().take_while(space).count();
+    part[start..end.max(start)]
+        .iter()
+        .map(|&(character, _)| character)
+        .collect()
+}
+
+fn escape(text: &str, name: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    let last = text.chars().count().saturating_sub(1);
+    for (i, character) in text.chars().enumerate() {
+        match character {
+            '\\' => escaped.push_str("\\\\"),
+            '=' => escaped.push_str("\\="),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            ' ' if i == 0 || i == last => escaped.push_str("\\ "),
+            '#' if name && i == 0 => escaped.push_str("\\#"),
+            character => escaped.push(character),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,695 @@
+//! TOML documents.
+
+use std::collections::btree_map::{BTreeMap, Entry};
+use std::collections::HashSet;
+use std::fmt::{self, Write};
+
+use crate::Json;
+
+const MAX_DEPTH: usize = 128;
+
+/// An error of reading or writing a TOML document.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TomlError {
+    /// The document is invalid.
+    Invalid {
+        /// The line, counting from one, at which the document stops being valid.
+        line: usize,
+    },
+    /// The value of a parameter cannot be represented.
+    Unsupported {
+        /// The path of the parameter.
+        path: String,
+    },
+}
+
+type Table = BTreeMap<String, Json>;
+
+/// Parse a document into a JSON object.
+///
+/// Tables become objects, arrays of tables become arrays of objects, and dates
+/// and times become strings. Arrays and inline tables nested more than 128
+/// levels deep are rejected, and so are tables defined more than once.
+pub fn parse(text: &str) -> Result<Json, TomlError> {
+    let mut parser = Parser {
+        text,
+        position: 0,
+        depth: 0,
+    };
+    let mut root = Table::new();
+    let mut path = Vec::new();
+    let mut defined = HashSet::new();
+    loop {
+        parser.skip_lines();
+        if parser.peek().is_none() {
+            return Ok(Json::Object(root));
+        }
+        if parser.eat('[') {
+            let array = parser.eat('[');
+            parser.skip();
+            path = parser.key()?;
+            parser.skip();
+            parser.expect(']')?;
+            if array {
+                parser.expect(']')?;
+                defined.retain(|other: &Vec<String>| !other.starts_with(&path));
+            } else if !defined.insert(path.clone()) {
+                return Err(parser.error());
+            }
+            let (name, parent) = match path.split_last() {
+                Some(pair) => pair,
+                _ => unreachable!(),
+            };
+            let parent = parser.table(&mut root, parent)?;
+            match (parent.entry(name.clone()), array) {
+                (Entry::Vacant(entry), true) => {
+                    entry.insert(Json::Array(vec![Json::Object(Table::new())]));
+                }
+                (Entry::Occupied(mut entry), true) => match entry.get_mut() {
+                    Json::Array(values) if values.iter().all(is_table) => {
+                        values.push(Json::Object(Table::new()));
+                    }
+                    _ => return Err(parser.error()),
+                },
+                (entry, _) => {
+                    if !is_table(entry.or_insert_with(|| Json::Object(Table::new()))) {
+                        return Err(parser.error());
+                    }
+                }
+            }
+        } else {
+            let table = parser.table(&mut root, &path)?;
+            parser.member(table)?;
+        }
+        parser.end_line()?;
+    }
+}
+
+/// Write a JSON object as a document.
+///
+/// Objects become tables and nonempty arrays of objects become arrays of
+/// tables. Nulls cannot be represented and result in an error naming the path
+/// of the member containing them.
+pub fn write(value: &Json) -> Result<String, TomlError> {
+    let mut output = String::new();
+    if let Json::Object(members) = value {
+        write_table(&mut output, "", "", members)?;
+    }
+    Ok(output)
+}
+
+impl fmt::Display for TomlError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TomlError::Invalid { line } => write!(formatter, "invalid TOML at line {line}"),
+            TomlError::Unsupported { path } => {
+                write!(formatter, "the value of {path} cannot be written as TOML")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TomlError {}
+
+struct Parser<'l> {
+    text: &'l str,
+    position: usize,
+    depth: usize,
+}
+
+impl<'l> Parser<'l> {
+    fn table<'t>(
+        &self,
+        mut table: &'t mut Table,
+        path: &[String],
+    ) -> Result<&'t mut Table, TomlError> {
+        for name in path {
+            let mut value = table
+                .entry(name.clone())
+                .or_insert_with(|| Json::Object(Table::new()));
+            if let Json::Array(values) = value {
+                value = values.last_mut().ok_or_else(|| self.error())?;
+            }
+            table = match value {
+                Json::Object(members) => members,
+                _ => return Err(self.error()),
+            };
+        }
+        Ok(table)
+    }
+
+    fn member(&mut self, table: &mut Table) -> Result<(), TomlError> {
+        let path = self.key()?;
+        self.skip();
+        self.expect('=')?;
+        self.skip();
+        let value = self.value()?;
+        let (name, parent) = match path.split_last() {
+            Some(pair) => pair,
+            _ => unreachable!(),
+        };
+        match self.table(table, parent)?.entry(name.clone()) {
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+                Ok(())
+            }
+            _ => Err(self.error()),
+        }
+    }
+
+    fn key(&mut self) -> Result<Vec<String>, TomlError> {
+        let mut path = Vec::new();
+        loop {
+            let name = match self.peek() {
+                Some('"') => self.basic_string()?,
+                Some('\'') => self.literal_string()?,
+                _ => match self.take(|character| {
+                    character.is_ascii_alphanumeric() || matches!(character, '_' | '-')
+                }) {
+                    "" => return Err(self.error()),
+                    name => name.to_string(),
+                },
+            };
+            path.push(name);
+            self.skip();
+            if !self.eat('.') {
+                return Ok(path);
+            }
+            self.skip();
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, TomlError> {
+        match self.peek().ok_or_else(|| self.error())? {
+            '"' if self.rest().starts_with("\"\"\"") => {
+                self.multiline_basic_string().map(Json::String)
+            }
+            '"' => self.basic_string().map(Json::String),
+            '\'' if self.rest().starts_with("'''") => {
+                self.multiline_literal_string().map(Json::String)
+            }
+            '\'' => self.literal_string().map(Json::String),
+            '[' => self.nested(Parser::array),
+            '{' => self.nested(Parser::inline_table),
+            _ => self.scalar(),
+        }
+    }
+
+    fn nested(
+        &mut self,
+        parse: fn(&mut Self) -> Result<Json, TomlError>,
+    ) -> Result<Json, TomlError> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error());
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn array(&mut self) -> Result<Json, TomlError> {
+        self.position += 1;
+        let mut values = Vec::new();
+        loop {
+            self.skip_lines();
+            if self.eat(']') {
+                return Ok(Json::Array(values));
+            }
+            values.push(self.value()?);
+            self.skip_lines();
+            if self.eat(']') {
+                return Ok(Json::Array(values));
+            }
+            self.expect(',')?;
+        }
+    }
+
+    fn inline_table(&mut self) -> Result<Json, TomlError> {
+        self.position += 1;
+        let mut members = Table::new();
+        self.skip();
+        if self.eat('}') {
+            return Ok(Json::Object(members));
+        }
+        loop {
+            self.skip();
+            self.member(&mut members)?;
+            self.skip();
+            if self.eat('}') {
+                return Ok(Json::Object(members));
+            }
+            self.expect(',')?;
+        }
+    }
+
+    fn scalar(&mut self) -> Result<Json, TomlError> {
+        let start = self.position;
+        let accept = |character: char| {
+            character.is_ascii_alphanumeric() || matches!(character, '+' | '-' | '.' | ':' | '_')
+        };
+        let mut token = self.take(accept).to_string();
+        let bytes = token.as_bytes();
+        let is_date = bytes.len() >= 10 && bytes[4] == b'-' && bytes[7] == b'-';
+        if token.len() == 10
+            && is_date
+            && self.rest().starts_with(' ')
+            && self.rest()[1..].starts_with(|character: char| character.is_ascii_digit())
+        {
+            self.position += 1;
+            token = format!("{token} {}", self.take(accept));
+        }
+        let value = match token.as_str() {
+            "true" => Some(Json::Bool(true)),
+            "false" => Some(Json::Bool(false)),
+            "inf" | "+inf" => Some(Json::Float(f64::INFINITY)),
+            "-inf" => Some(Json::Float(f64::NEG_INFINITY)),
+            "nan" | "+nan" | "-nan" => Some(Json::Float(f64::NAN)),
+            _ if is_date || token.as_bytes().get(2) == Some(&b':') => {
+                Some(Json::String(token.clone()))
+            }
+            _ => number(&token),
+        };
+        value.ok_or(TomlError::Invalid {
+            line: self.line(start),
+        })
+    }
+
+    fn basic_string(&mut self) -> Result<String, TomlError> {
+        self.expect('"')?;
+        let mut value = String::new();
+        loop {
+            match self.next()? {
+                '"' => return Ok(value),
+                '\\' => value.push(self.escape()?),
+                '\n' => return Err(self.error()),
+                character => value.push(character),
+            }
+        }
+    }
+
+    fn multiline_basic_string(&mut self) -> Result<String, TomlError> {
+        self.position += 3;
+        self.eat_newline();
+        let mut value = String::new();
+        loop {
+            if self.rest().starts_with("\"\"\"") && !self.rest().starts_with("\"\"\"\"") {
+                self.position += 3;
+                return Ok(value);
+            }
+            match self.next()? {
+                '\\' if self
+                    .rest()
+                    .trim_start_matches([' ', '\t'])
+                    .starts_with(['\n', '\r']) =>
+                {
+                    self.take(|character| matches!(character, ' ' | '\t' | '\n' | '\r'));
+                }
+                '\\' => value.push(self.escape()?),
+                character => value.push(character),
+            }
+        }
+    }
+
+    fn literal_string(&mut self) -> Result<String, TomlError> {
+        self.expect('\'')?;
+        match self.rest().find(['\'', '\n']) {
+            Some(length) if self.rest()[length..].starts_with('\'') => {
+                let value = self.rest()[..length].to_string();
+                self.position += length + 1;
+                Ok(value)
+            }
+            _ => Err(self.error()),
+        }
+    }
+
+    fn multiline_literal_string(&mut self) -> Result<String, TomlError> {
+        self.position += 3;
+        self.eat_newline();
+        let mut length = self.rest().find("'''").ok_or_else(|| self.error())?;
+        while self.rest()[length + 3..].starts_with('\'') && length + 3 < self.rest().len() {
+            length += 1;
+        }
+        let value = self.rest()[..length].to_string();
+        self.position += length + 3;
+        Ok(value)
+    }
+
+    fn escape(&mut self) -> Result<char, TomlError> {
+        Ok(match self.next()? {
+            'b' => '\u{8}',
+            't' => '\t',
+            'n' => '\n',
+            'f' => '\u{c}',
+            'r' => '\r',
+            'e' => '\u{1b}',
+            '"' => '"',
+            '\\' => '\\',
+            'u' => self.unicode(4)?,
+            'U' => self.unicode(8)?,
+            _ => return Err(self.error()),
+        })
+    }
+
+    fn unicode(&mut self, length: usize) -> Result<char, TomlError> {
+        let digits = self
+            .text
+            .get(self.position..self.position + length)
+            .ok_or_else(|| self.error())?;
+        let code = u32::from_str_radix(digits, 16).map_err(|_| self.error())?;
+        self.position += length;
+        char::from_u32(code).ok_or_else(|| self.error())
+    }
+
+    fn end_line(&mut self) -> Result<(), TomlError> {
+        self.skip();
+        if self.peek() == Some('#') {
+            self.position += self.rest().find('\n').unwrap_or(self.rest().len());
+        }
+        match self.peek() {
+            None => Ok(()),
+            _ if self.eat_newline() => Ok(()),
+            _ => Err(self.error()),
+        }
+    }
+
+    fn eat_newline(&mut self) -> bool {
+        self.eat('\n') || (self.rest().starts_with("\r\n") && self.eat('\r') && self.eat('\n'))
+    }
+
+    fn expect(&mut self, character: char) -> Result<(), TomlError> {
+        match self.eat(character) {
+            true => Ok(()),
+            _ => Err(self.error()),
+        }
+    }
+
+    fn eat(&mut self, character: char) -> bool {
+        let found = self.peek() == Some(character);
+        if found {
+            self.position += character.len_utf8();
+        }
+        found
+    }
+
+    fn next(&mut self) -> Result<char, TomlError> {
+        let character = self.peek().ok_or_else(|| self.error())?;
+        self.position += character.len_utf8();
+        Ok(character)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn rest(&self) -> &'l str {
+        &self.text[self.position..]
+    }
+
+    fn take<F: Fn(char) -> bool>(&mut self, accept: F) -> &'l str {
+        let start = self.position;
+        let length = self
+            .rest()
+            .find(|character| !accept(character))
+            .unwrap_or(self.rest().len());
+        self.position += length;
+        &self.text[start..self.position]
+    }
+
+    fn skip(&mut self) {
+        self.take(|character| matches!(character, ' ' | '\t'));
+    }
+
+    fn skip_lines(&mut self) {
+        loop {
+            self.take(|character| matches!(character, ' ' | '\t' | '\n' | '\r'));
+            match self.peek() {
+                Some('#') => {
+                    self.take(|character| character != '\n');
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn line(&self, position: usize) -> usize {
+        self.text[..position].matches('\n').count() + 1
+    }
+
+    fn error(&self) -> TomlError {
+        TomlError::Invalid {
+            line: self.line(self.position),
+        }
+    }
+}
+
+fn is_table(value: &Json) -> bool {
+    matches!(value, Json::Object(_))
+}
+
+fn is_array_of_tables(value: &Json) -> bool {
+    matches!(value, Json::Array(values) if !values.is_empty() && values.iter().all(is_table))
+}
+
+fn write_table(
+    output: &mut String,
+    header: &str,
+    path: &str,
+    members: &Table,
+) -> Result<(), TomlError> {
+    for (name, value) in members {
+        if !is_table(value) && !is_array_of_tables(value) {
+            let value = write_value(value, &format!("{path}{name}"))?;
+            let _ = writeln!(output, "{} = {value}", write_key(name));
+        }
+    }
+    for (name, value) in members {
+        let header = format!("{header}{}", write_key(name));
+        let path = format!("{path}{name}");
+        let (tables, array) = match value {
+            Json::Object(members) => (vec![members], false),
+            Json::Array(values) if is_array_of_tables(value) => (
+                values
+                    .iter()
+                    .filter_map(|value| match value {
+                        Json::Object(members) => Some(members),
+                        _ => None,
+                    })
+                    .collect(),
+                true,
+            ),
+            _ => continue,
+        };
+        for members in tables {
+            if !output.is_empty() {
+                output.push('\n');
+            }
+            let _ = match array {
+                true => writeln!(output, "[[{header}]]"),
+                _ => writeln!(output, "[{header}]"),
+            };
+            write_table(output, &format!("{header}."), &format!("{path}."), members)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_value(value: &Json, path: &str) -> Result<String, TomlError> {
+    Ok(match value {
+        Json::Null => {
+            return Err(TomlError::Unsupported {
+                path: path.to_string(),
+            })
+        }
+        Json::Bool(value) => value.to_string(),
+        Json::Integer(value) => value.to_string(),
+        Json::Float(value) if value.is_nan() => "nan".to_string(),
+        Json::Float(value) if value.is_infinite() => match *value > 0.0 {
+            true => "inf".to_string(),
+            _ => "-inf".to_string(),
+        },
+        Json::Float(value) => format!("{value:?}"),
+        Json::String(value) => write_string(value),
+        Json::Array(values) => {
+            let values = values
+                .iter()
+                .map(|value| write_value(value, path))
+                .collect::<Result<Vec<_>, _>>()?;
+            format!("[{}]", values.join(", "))
+        }
+        Json::Object(members) if members.is_empty() => "{}".to_string(),
+        Json::Object(members) => {
+            let members = members
+                .iter()
+                .map(|(name, value)| {
+                    let value = write_value(value, &format!("{path}.{name}"))?;
+                    Ok(format!("{} = {value}", write_key(name)))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            format!("{{ {} }}", members.join(", "))
+        }
+    })
+}
+
+fn write_key(name: &str) -> String {
+    match !name.is_empty()
+        && name
+            .chars()
+            .all(|character| character.is_ascii_alphanumeric() || matches!(character, '_' | '-'))
+    {
+        true => name.to_string(),
+        _ => write_string(name),
+    }
+}
+
+fn write_string(value: &str) -> String {
+    let mut output = String::from("\"");
+    for character in value.chars() {
+        match character {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            character if character < ' ' || character == '\u{7f}' => {
+                let _ = write!(output, "\\u{:04X}", character as u32);
+            }
+            character => output.push(character),
+        }
+    }
+    output.push('"');
+    output
+}
+
+fn number(token: &str) -> Option<Json> {
+    let valid = |digits: &str, radix: u32| {
+        !digits.is_empty()
+            && !digits.starts_with('_')
+            && !digits.ends_with('_')
+            && !digits.contains("__")
+            && digits
+                .chars()
+                .all(|character| character == '_' || character.is_digit(radix))
+    };
+    for (prefix, radix) in [("0x", 16), ("0o", 8), ("0b", 2)] {
+        if let Some(digits) = token.strip_prefix(prefix) {
+            return match valid(digits, radix) {
+                true => i64::from_str_radix(&digits.replace('_', ""), radix)
+                    .ok()
+                    .map(Json::Integer),
+                _ => None,
+            };
+        }
+    }
+    let unsigned = token.strip_prefix(['+', '-']).unwrap_or(token);
+    if unsigned.len() > 1
+        && unsigned.starts_with('0')
+        && unsigned[1..].starts_with(|character: char| character.is_ascii_digit())
+    {
+        return None;
+    }
+    let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, Some(exponent)),
+        _ => (unsigned, None),
+    };
+    let (integer, fraction) = match mantissa.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        _ => (mantissa, None),
+    };
+    if !valid(integer, 10)
+        || !fraction.is_none_or(|fraction| valid(fraction, 10))
+        || !exponent
+            .is_none_or(|exponent| valid(exponent.strip_prefix(['+', '-']).unwrap_or(exponent), 10))
+    {
+        return None;
+    }
+    let token = token.replace('_', "");
+    match (fraction, exponent) {
+        (None, None) => token.parse().ok().map(Json::Integer),
+        _ => token.parse().ok().map(Json::Float),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TomlError;
+
+    #[test]
+    fn parse() {
+        macro_rules! test(
+            ($text:expr, Ok($expected:expr)) => (
+                assert_eq!(
+                    super::parse($text).map(|value| value.to_string()),
+                    Ok($expected.to_string()),
+                )
+            );
+            ($text:expr, Err($line:expr)) => (
+                assert_eq!(super::parse($text), Err(TomlError::Invalid { line: $line }))
+            );
+        );
+
+        test!("", Ok("{}"));
+        test!(
+            "# comment\na = 1 # comment\nb.c = 'x'\n\"d e\" = true\n",
+            Ok(r#"{"a":1,"b":{"c":"x"},"d e":true}"#)
+        );
+        test!(
+            "[x.y]\nz = [1, 2,\n  3,]\n[x]\nw = { v = -1.5e3, u = \"\\u00e9\\n\" }\n",
+            Ok(r#"{"x":{"w":{"u":"é\n","v":-1500.0},"y":{"z":[1,2,3]}}}"#)
+        );
+        test!(
+            "[[p]]\nn = 1_000\n[[p]]\nn = 0o17\n[[p]]\nn = 0b11\n",
+            Ok(r#"{"p":[{"n":1000},{"n":15},{"n":3}]}"#)
+        );
+        test!(
+            "a = 1979-05-27T07:32:00Z\nb = 1979-05-27 07:32:00\nc = 07:32:00\n",
+            Ok(r#"{"a":"1979-05-27T07:32:00Z","b":"1979-05-27 07:32:00","c":"07:32:00"}"#)
+        );
+        test!(
+            "a = \"\"\"\nx \\\n   y\"\"\"\nb = '''\nC:\\z'''\n",
+            Ok(r#"{"a":"x y","b":"C:\\z"}"#)
+        );
+        test!("a = inf\nb = -nan", Ok(r#"{"a":null,"b":null}"#));
+        test!("a = 1\na = 2\n", Err(2));
+        test!("a = 01\n", Err(1));
+        test!("a = 0x_1\n", Err(1));
+        test!("a = 1 b = 2\n", Err(1));
+        test!("a = 1\n[a]\n", Err(2));
+        test!("a = \"x\n\"\n", Err(2));
+        test!("[a\n", Err(1));
+        test!("[a]\nb = 1\n[a]\nc = 2\n", Err(3));
+        test!("[a.b]\n[a]\n[a.b]\n", Err(3));
+        test!(
+            "[[p]]\n[p.q]\n[[p]]\n[p.q]\n",
+            Ok(r#"{"p":[{"q":{}},{"q":{}}]}"#)
+        );
+        test!(&format!("a = {}", "[".repeat(100000)), Err(1));
+        test!(&format!("a = {}", "{ b = ".repeat(100000)), Err(1));
+    }
+
+    #[test]
+    fn write() {
+        use crate::Json;
+
+        macro_rules! test(
+            ($text:expr, $expected:expr) => (
+                assert_eq!(super::write(&super::parse($text).unwrap()), $expected)
+            );
+        );
+
+        test!("", Ok(String::new()));
+        test!(
+            "[a]\nb = [1, 2.5, inf]\n\"c d\" = { e = \"\\\"\\u0001\" }\n[[f]]\ng = true\n[[f]]\n",
+            Ok("[a]\nb = [1, 2.5, inf]\n\n[a.\"c d\"]\ne = \"\\\"\\u0001\"\n\n[[f]]\ng = true\n\n[[f]]\n"
+                .to_string())
+        );
+        assert_eq!(
+            super::write(&Json::Object(
+                [("a".to_string(), Json::Array(vec![Json::Null]))].into()
+            )),
+            Err(TomlError::Unsupported {
+                path: "a".to_string()
+            }),
+        );
+    }
+}
@@ -0,0 +1,302 @@
+//! Operations on values of registered types.
+//!
+//! A `dyn Any` cannot be inspected without knowing its concrete type, so the
+//! operations below recognize a fixed set of types: the primitive scalars,
+//! `String`, `&'static str`, and vectors of these.
+
+use std::any::Any;
+use std::mem::size_of;
+use std::num::NonZero;
+
+#[cfg(feature = "json")]
+use crate::Json;
+use crate::TypedRef;
+
+macro_rules! integers(
+    ($callback:ident) => (
+        $callback!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize)
+    );
+);
+
+macro_rules! scalars(
+    ($callback:ident) => (
+        $callback!(
+            bool, char, f32, f64, i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize
+        )
+    );
+);
+
+/// Clone a value.
+pub fn clone(value: &dyn Any) -> Option<Box<dyn Any>> {
+    macro_rules! scalar(
+        ($($kind:ty),*) => ($(
+            if let Some(value) = value.downcast_ref::<$kind>() {
+                return Some(Box::new(*value));
+            }
+            if let Some(value) = value.downcast_ref::<Vec<$kind>>() {
+                return Some(Box::new(value.clone()));
+            }
+        )*);
+    );
+    scalars!(scalar);
+    macro_rules! other(
+        ($($kind:ty),*) => ($(
+            if let Some(value) = value.downcast_ref::<$kind>() {
+                return Some(Box::new(value.clone()));
+            }
+        )*);
+    );
+    other!(
+        String,
+        &'static str,
+        Vec<String>,
+        Vec<&'static str>,
+        crate::Clear
+    );
+    #[cfg(feature = "json")]
+    other!(Json, Vec<Json>);
+    None
+}
+
+/// Convert a value into one that can be shared between threads.
+pub fn into_sync(mut value: Box<dyn Any>) -> Result<Box<dyn Any + Send + Sync>, Box<dyn Any>> {
+    macro_rules! other(
+        ($($kind:ty),*) => ($(
+            value = match value.downcast::<$kind>() {
+                Ok(value) => return Ok(value),
+                Err(value) => value,
+            };
+        )*);
+    );
+    macro_rules! scalar(
+        ($($kind:ty),*) => ($(
+            other!($kind, Vec<$kind>);
+        )*);
+    );
+    scalars!(scalar);
+    other!(String, &'static str, Vec<String>, Vec<&'static str>);
+    #[cfg(feature = "json")]
+    other!(Json, Vec<Json>);
+    Err(value)
+}
+
+/// Convert an integer into a nonzero integer of a specific type.
+///
+/// If the integer is zero or the type does not match, `None` is returned.
+pub fn nonzero<T: Any + Copy>(value: &dyn Any) -> Option<T> {
+    macro_rules! integer(
+        ($($kind:ty),*) => ($(
+            if let Some(value) = value.downcast_ref::<$kind>() {
+                let value = NonZero::new(*value)?;
+                return (&value as &dyn Any).downcast_ref().copied();
+            }
+        )*);
+    );
+    integers!(integer);
+    None
+}
+
+/// Categorize a value.
+///
+/// Integers that do not fit in `i64` are not categorized.
+pub fn typed(value: &dyn Any) -> Option<TypedRef<'_>> {
+    macro_rules! integer(
+        ($($kind:ty),*) => ($(
+            if let Some(value) = value.downcast_ref::<$kind>() {
+                return i64::try_from(*value).ok().map(TypedRef::Int);
+            }
+        )*);
+    );
+    integers!(integer);
+    if let Some(value) = value.downcast_ref::<f32>() {
+        return Some(TypedRef::Float(*value as f64));
+    }
+    if let Some(value) = value.downcast_ref::<f64>() {
+        return Some(TypedRef::Float(*value));
+    }
+    if let Some(value) = value.downcast_ref::<bool>() {
+        return Some(TypedRef::Bool(*value));
+    }
+    if let Some(value) = value.downcast_ref::<String>() {
+        return Some(TypedRef::Str(value));
+    }
+    if let Some(value) = value.downcast_ref::<&'static str>() {
+        return Some(TypedRef::Str(value));
+    }
+    None
+}
+
+/// Compare two values of the same type.
+pub fn eq(one: &dyn Any, other: &dyn Any) -> Option<bool> {
+    macro_rules! other(
+        ($($kind:ty),*) => ($(
+            if let (Some(one), Some(other)) = (one.downcast_ref::<$kind>(), other.downcast_ref::<$kind>()) {
+                return Some(one == other);
+            }
+        )*);
+    );
+    macro_rules! scalar(
+        ($($kind:ty),*) => ($(
+            other!($kind, Vec<$kind>);
+        )*);
+    );
+    scalars!(scalar);
+    other!(String, &'static str, Vec<String>, Vec<&'static str>);
+    #[cfg(feature = "json")]
+    other!(Json, Vec<Json>);
+    None
+}
+
+/// Check if a value is an empty string or vector.
+pub fn is_empty(value: &dyn Any) -> Option<bool> {
+    macro_rules! scalar(
+        ($($kind:ty),*) => ($(
+            if let Some(value) = value.downcast_ref::<Vec<$kind>>() {
+                return Some(value.is_empty());
+            }
+        )*);
+    );
+    scalars!(scalar);
+    if let Some(value) = value.downcast_ref::<String>() {
+        return Some(value.is_empty());
+    }
+    if let Some(value) = value.downcast_ref::<&'static str>() {
+        return Some(value.is_empty());
+    }
+    if let Some(value) = value.downcast_ref::<Vec<String>>() {
+        return Some(value.is_empty());
+    }
+    if let Some(value) = value.downcast_ref::<Vec<&'static str>>() {
+        return Some(value.is_empty());
+    }
+    None
+}
+
+/// Render a value as a string.
+///
+/// Scalars and strings are rendered via `Display` and vectors via `Debug`.
+pub fn display(value: &dyn Any) -> Option<String> {
+    macro_rules! scalar(
+        ($($kind:ty),*) => ($(
+            if let Some(value) = value.downcast_ref::<$kind>() {
+                return Some(value.to_string());
+            }
+            if let Some(value) = value.downcast_ref::<Vec<$kind>>() {
+                return Some(format!("{value:?}"));
+            }
+        )*);
+    );
+    scalars!(scalar);
+    if let Some(value) = value.downcast_ref::<String>() {
+        return Some(value.clone());
+    }
+    if let Some(value) = value.downcast_ref::<&'static str>() {
+        return Some(value.to_string());
+    }
+    if let Some(value) = value.downcast_ref::<Vec<String>>() {
+        return Some(format!("{value:?}"));
+    }
+    if let Some(value) = value.downcast_ref::<Vec<&'static str>>() {
+        return Some(format!("{value:?}"));
+    }
+    None
+}
+
+/// Return the name of the type of a value.
+pub fn name(value: &dyn Any) -> Option<&'static str> {
+    macro_rules! scalar(
+        ($($kind:ty),*) => ($(
+            if value.is::<$kind>() {
+                return Some(std::any::type_name::<$kind>());
+            }
+            if value.is::<Vec<$kind>>() {
+                return Some(std::any::type_name::<Vec<$kind>>());
+            }
+        )*);
+    );
+    scalars!(scalar);
+    macro_rules! other(
+        ($($kind:ty),*) => ($(
+            if value.is::<$kind>() {
+                return Some(std::any::type_name::<$kind>());
+            }
+        )*);
+    );
+    other!(
+        String,
+        &'static str,
+        Vec<String>,
+        Vec<&'static str>,
+        crate::Options
+    );
+    None
+}
+
+/// Compute the size of a value including the memory it owns.
+pub fn size(value: &dyn Any) -> Option<usize> {
+    macro_rules! scalar(
+        ($($kind:ty),*) => ($(
+            if value.is::<$kind>() {
+                return Some(size_of::<$kind>());
+            }
+            if let Some(value) = value.downcast_ref::<Vec<$kind>>() {
+                return Some(size_of::<Vec<$kind>>() + value.capacity() * size_of::<$kind>());
+            }
+        )*);
+    );
+    scalars!(scalar);
+    if let Some(value) = value.downcast_ref::<String>() {
+        return Some(size_of::<String>() + value.capacity());
+    }
+    if value.is::<&'static str>() {
+        return Some(size_of::<&'static str>());
+    }
+    if let Some(value) = value.downcast_ref::<Vec<String>>() {
+        return Some(
+            size_of::<Vec<String>>()
+                + value.capacity() * size_of::<String>()
+                + value.iter().map(String::capacity).sum::<usize>(),
+        );
+    }
+    if let Some(value) = value.downcast_ref::<Vec<&'static str>>() {
+        return Some(size_of::<Vec<&'static str>>() + value.capacity() * size_of::<&'static str>());
+    }
+    None
+}
+
+/// Convert a value to JSON.
+///
+/// Integers that do not fit in `i64` are converted to floating-point numbers.
+#[cfg(feature = "json")]
+pub fn json(value: &dyn Any) -> Option<Json> {
+    macro_rules! other(
+        ($($kind:ty => $convert:expr),*) => ($(
+            if let Some(value) = value.downcast_ref::<$kind>() {
+                return Some($convert(value));
+            }
+            if let Some(value) = value.downcast_ref::<Vec<$kind>>() {
+                return Some(Json::Array(value.iter().map($convert).collect()));
+            }
+        )*);
+    );
+    macro_rules! scalar(
+        ($($kind:ty),*) => ($(
+            let convert = |value: &$kind| match i64::try_from(*value) {
+                Ok(value) => Json::Integer(value),
+                _ => Json::Float(*value as f64),
+            };
+            other!($kind => convert);
+        )*);
+    );
+    integers!(scalar);
+    other!(
+        bool => |value: &bool| Json::Bool(*value),
+        char => |value: &char| Json::String(value.to_string()),
+        f32 => |value: &f32| Json::Float(*value as f64),
+        f64 => |value: &f64| Json::Float(*value),
+        String => |value: &String| Json::String(value.clone()),
+        &'static str => |value: &&str| Json::String(value.to_string()),
+        Json => Json::clone
+    );
+    None
+}